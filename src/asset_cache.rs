@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::DebugRenderContext;
+
+/// sizes and colors are rounded to this before being compared
+const KEY_STEPS:f32 = 1000.0;
+
+fn quantize(value: f32) -> i32 {
+    (value * KEY_STEPS).round() as i32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MeshKey {
+    Cuboid([i32; 3]),
+    Sphere(i32)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct MaterialKey {
+    color:[i32; 3],
+    double_sided:bool
+}
+
+/// Meshes and materials by shape and color, so identical walls share their handles.
+#[derive(Resource, Default)]
+pub struct AssetCache {
+    meshes:HashMap<MeshKey, Handle<Mesh>>,
+    materials:HashMap<MaterialKey, Handle<StandardMaterial>>
+}
+
+/// The cache together with the asset collections it adds to.
+#[derive(SystemParam)]
+pub struct CachedAssets<'w> {
+    cache:ResMut<'w, AssetCache>,
+    meshes:ResMut<'w, Assets<Mesh>>,
+    materials:ResMut<'w, Assets<StandardMaterial>>
+}
+impl CachedAssets<'_> {
+    pub fn cuboid(&mut self, size: Vec3) -> Handle<Mesh> {
+        let key = MeshKey::Cuboid([quantize(size.x), quantize(size.y), quantize(size.z)]);
+        let meshes = &mut self.meshes;
+        self.cache.meshes.entry(key)
+            .or_insert_with(|| meshes.add(Mesh::from(Cuboid::new(size.x, size.y, size.z))))
+            .clone()
+    }
+
+    pub fn sphere(&mut self, radius: f32) -> Handle<Mesh> {
+        let key = MeshKey::Sphere(quantize(radius));
+        let meshes = &mut self.meshes;
+        self.cache.meshes.entry(key)
+            .or_insert_with(|| meshes.add(Mesh::from(Sphere::new(radius))))
+            .clone()
+    }
+
+    pub fn material(&mut self, (r, g, b): (f32, f32, f32), double_sided: bool) -> Handle<StandardMaterial> {
+        let key = MaterialKey {
+            color: [quantize(r), quantize(g), quantize(b)],
+            double_sided
+        };
+        let materials = &mut self.materials;
+        self.cache.materials.entry(key)
+            .or_insert_with(|| materials.add(StandardMaterial {
+                base_color: Color::srgb(r, g, b),
+                double_sided,
+                ..Default::default()
+            }))
+            .clone()
+    }
+}
+
+/// Keeps the `AssetCache`.
+pub struct AssetCachePlugin;
+
+impl Plugin for AssetCachePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AssetCache>();
+    }
+}
+
+#[derive(Component)]
+struct AssetCountText;
+
+/// Shows how many meshes and materials exist while the debug rendering is on.
+pub struct AssetCountPlugin;
+
+impl Plugin for AssetCountPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup,setup_asset_count)
+            .add_systems(Update,asset_count);
+    }
+}
+
+fn setup_asset_count(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(Color::srgb(0.8, 0.8, 0.8)),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        },
+        Visibility::Hidden
+    ))
+        .insert(AssetCountText);
+}
+
+fn asset_count(
+    debug_render: Res<DebugRenderContext>,
+    cache: Res<AssetCache>,
+    meshes: Res<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+    mut query_text: Query<(&mut Text, &mut Visibility), With<AssetCountText>>,
+){
+    let Ok((mut text, mut visibility)) = query_text.get_single_mut() else {
+        return;
+    };
+    if !debug_render.enabled {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Visible;
+    text.0 = format!("meshes: {} ({} cached) materials: {} ({} cached)",
+                     meshes.len(), cache.meshes.len(),
+                     materials.len(), cache.materials.len());
+}
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::state::GameState;
+
+const SETTINGS_FILE:&str = "settings.ron";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Confirm,
+    Pause,
+    Restart,
+    Quit,
+    ToggleMode,
+    ToggleDebug,
+    Bindings,
+    KeepBall,
+    Editor,
+    Endless,
+}
+
+impl Action {
+    pub const ALL:[Action; 14] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Confirm,
+        Action::Pause,
+        Action::Restart,
+        Action::Quit,
+        Action::ToggleMode,
+        Action::ToggleDebug,
+        Action::Bindings,
+        Action::KeepBall,
+        Action::Editor,
+        Action::Endless,
+    ];
+
+    fn default_keys(&self) -> Vec<KeyCode> {
+        match self {
+            Action::MoveForward => vec![KeyCode::ArrowUp, KeyCode::KeyW],
+            Action::MoveBack => vec![KeyCode::ArrowDown, KeyCode::KeyS],
+            Action::MoveLeft => vec![KeyCode::ArrowLeft, KeyCode::KeyA],
+            Action::MoveRight => vec![KeyCode::ArrowRight, KeyCode::KeyD],
+            Action::Confirm => vec![KeyCode::Enter, KeyCode::Space],
+            Action::Pause => vec![KeyCode::Escape, KeyCode::KeyP],
+            Action::Restart => vec![KeyCode::KeyR],
+            Action::Quit => vec![KeyCode::KeyQ],
+            Action::ToggleMode => vec![KeyCode::Tab],
+            Action::ToggleDebug => vec![KeyCode::F3],
+            Action::Bindings => vec![KeyCode::KeyK],
+            Action::KeepBall => vec![KeyCode::F5],
+            Action::Editor => vec![KeyCode::F2],
+            Action::Endless => vec![KeyCode::KeyE],
+        }
+    }
+}
+
+/// Keys bound to each action, stored in `settings.ron` next to the executable's working directory.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub bindings:BTreeMap<Action, Vec<KeyCode>>
+}
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL.iter()
+                .map(|action| (*action, action.default_keys()))
+                .collect()
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn load() -> Self {
+        let mut key_bindings = match std::fs::read_to_string(SETTINGS_FILE) {
+            Ok(text) => match ron::from_str::<KeyBindings>(&text) {
+                Ok(key_bindings) => key_bindings,
+                Err(error) => {
+                    warn!("ignoring {}: {}", SETTINGS_FILE, error);
+                    KeyBindings::default()
+                }
+            },
+            Err(_) => KeyBindings::default()
+        };
+        //actions added after the file was written
+        for action in Action::ALL {
+            key_bindings.bindings.entry(action).or_insert_with(|| action.default_keys());
+        }
+        key_bindings
+    }
+
+    pub fn save(&self) {
+        let text = match ron::ser::to_string_pretty(self, PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(error) => {
+                warn!("could not write key bindings: {}", error);
+                return;
+            }
+        };
+        if let Err(error) = std::fs::write(SETTINGS_FILE, text) {
+            warn!("could not write {}: {}", SETTINGS_FILE, error);
+        }
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings.get(&action).map(|keys| keys.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn pressed(&self, keyboard_input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        keyboard_input.any_pressed(self.keys(action).iter().copied())
+    }
+
+    pub fn just_pressed(&self, keyboard_input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        keyboard_input.any_just_pressed(self.keys(action).iter().copied())
+    }
+}
+
+/// State of the rebinding screen.
+#[derive(Resource, Default)]
+pub struct Rebinding {
+    selected:usize,
+    waiting:bool
+}
+
+#[derive(Component)]
+struct BindingRow(usize);
+
+pub fn spawn_bindings_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut rebinding: ResMut<Rebinding>,
+){
+    *rebinding = Rebinding::default();
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            StateScoped(GameState::Bindings)
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Key bindings"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 40.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 0.8, 0.0))
+            ));
+            for index in 0..Action::ALL.len() {
+                parent.spawn((
+                    Text::new(""),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 22.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(0.5, 0.5, 1.0)),
+                    BindingRow(index)
+                ));
+            }
+            parent.spawn((
+                Text::new("Up/Down select, Enter add key, Backspace clear, Esc save and back"),
+                TextFont {
+                    font,
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.8, 0.8, 0.8))
+            ));
+        });
+}
+
+/// The screen uses fixed keys so it stays usable whatever is bound.
+pub fn rebind(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut key_bindings: ResMut<KeyBindings>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    let action = Action::ALL[rebinding.selected];
+    if rebinding.waiting {
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            rebinding.waiting = false;
+        } else if let Some(key) = keyboard_input.get_just_pressed().next() {
+            let keys = key_bindings.bindings.entry(action).or_default();
+            if !keys.contains(key) {
+                keys.push(*key);
+            }
+            rebinding.waiting = false;
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+        rebinding.selected = (rebinding.selected + Action::ALL.len() - 1) % Action::ALL.len();
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        rebinding.selected = (rebinding.selected + 1) % Action::ALL.len();
+    }
+    if keyboard_input.just_pressed(KeyCode::Enter) {
+        rebinding.waiting = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::Backspace) {
+        key_bindings.bindings.insert(action, Vec::new());
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        key_bindings.save();
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+pub fn update_bindings_screen(
+    rebinding: Res<Rebinding>,
+    key_bindings: Res<KeyBindings>,
+    mut query_rows: Query<(&BindingRow, &mut Text, &mut TextColor)>,
+){
+    for (row, mut text, mut color) in query_rows.iter_mut() {
+        let action = Action::ALL[row.0];
+        let keys = if rebinding.waiting && row.0 == rebinding.selected {
+            "press a key...".to_string()
+        } else {
+            key_bindings.keys(action).iter()
+                .map(|key| format!("{:?}", key))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        text.0 = format!("{:?}: {}", action, keys);
+        color.0 = if row.0 == rebinding.selected {
+            Color::srgb(1.0, 1.0, 1.0)
+        } else {
+            Color::srgb(0.5, 0.5, 1.0)
+        };
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+
+use crate::state::GameState;
+use crate::tilt::{BoardTilt, MAX_TILT};
+
+#[derive(Component)]
+pub struct ThirdPersonTarget;
+
+#[derive(Component)]
+pub struct ThirdPersonCamera{
+    pub ideal_offset:Vec3,
+    pub ideal_lookat:Vec3,
+    pub current_lookat:Vec3,
+    pub lookat_aviabel:bool,
+    pub follow:f32
+}
+impl Default for ThirdPersonCamera {
+    fn default() -> Self {
+        Self {
+            ideal_offset: Vec3::new(0.0,2.0,6.0),
+            ideal_lookat: Vec3::new(0.0,0.0,-4.0),
+            current_lookat: Vec3::new(0.0,0.0,0.0),
+            lookat_aviabel: false,
+            follow: 1.2
+        }
+    }
+}
+
+/// Spawns the camera and light and keeps the camera behind the `ThirdPersonTarget`.
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BoardTilt>()
+            .add_systems(Startup,setup)
+            .add_systems(Update,move_camera.run_if(not(in_state(GameState::Editor))));
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+) {
+    //camera
+    //commands.spawn_bundle(PerspectiveCameraBundle{
+    //    ..Default::default()
+    //});
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        Transform::from_xyz(0.0,1.0,0.0).looking_at(Vec3::new(0.,0.,-4.), Vec3::Y)
+    ))
+        .insert(ThirdPersonCamera{..Default::default()});
+
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, 4.0, 0.0),
+            rotation: Quat::from_rotation_x(-std::f32::consts::FRAC_PI_4),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.02,
+    });
+}
+
+pub fn move_camera(
+    time:Res<Time>,
+    tilt:Res<BoardTilt>,
+    mut query_camera: Query<(&mut Transform, &mut ThirdPersonCamera), Without<ThirdPersonTarget>>,
+    query_target: Query<&Transform, With<ThirdPersonTarget>>
+){
+    let (mut camera_transform, mut thridperson) = query_camera.single_mut();
+    let Ok(target_transform) = query_target.get_single() else {
+        return;
+    };
+    let t = thridperson.follow * time.delta_secs();
+
+    let mut offset = thridperson.ideal_offset.clone();
+    offset += target_transform.translation;
+    offset = camera_transform.translation.lerp(offset,t);
+
+    let mut lookat = thridperson.ideal_lookat.clone();
+    lookat+= target_transform.translation;
+    if thridperson.lookat_aviabel {
+        lookat = thridperson.current_lookat.lerp(lookat, t);
+    } else{
+        thridperson.lookat_aviabel = true;
+    }
+
+    thridperson.current_lookat = lookat;
+
+    let transform = Transform::from_translation(offset).looking_at(lookat, Vec3::Y);
+    camera_transform.translation = transform.translation;
+    //tilt the horizon with the board
+    camera_transform.rotation = transform.rotation
+        * Quat::from_rotation_z(tilt.current.x * MAX_TILT)
+        * Quat::from_rotation_x(tilt.current.y * MAX_TILT);
+}
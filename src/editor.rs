@@ -0,0 +1,852 @@
+//! An editor for the current level, toggled with the `Editor` action while playing.
+//!
+//! Edits go to the loaded `Level` asset and the level is rebuilt from it, so what is saved
+//! is what is played when leaving the editor. Only what is in the level file can be edited,
+//! the contents of a glTF scene are shown but can't be picked.
+//!
+//! With `hot_reload` a save comes back as a reload of the file, which would undo everything
+//! edited in the meantime, so the editor puts its level back over it.
+
+use std::f32::consts::FRAC_PI_2;
+use std::path::{Path, PathBuf};
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::ecs::system::SystemParam;
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_rapier3d::prelude::*;
+
+use crate::asset_cache::CachedAssets;
+use crate::camera::ThirdPersonCamera;
+use crate::endless::EndlessRun;
+use crate::input::PlayerInput;
+use crate::level::{self, BlockDef, CurrentLevel, GateDef, Level, LevelElement, LevelEntity, PlatformDef};
+use crate::prefab::{self, BALL_COLOR};
+use crate::score::Score;
+use crate::state::GameState;
+
+/// edits that can be undone
+const HISTORY_LENGTH:usize = 100;
+const DEFAULT_SNAP:f32 = 0.5;
+/// degrees per press of the rotate key
+const ROTATE_STEP:f32 = 15.0;
+const CAMERA_SPEED:f32 = 8.0;
+/// radians per pixel the mouse moves
+const LOOK_SPEED:f32 = 0.005;
+const PICK_DISTANCE:f32 = 500.0;
+const SELECTED_COLOR:Color = Color::srgb(1.0, 0.8, 0.0);
+const GOAL_COLOR:Color = Color::srgb(0.0, 1.0, 0.3);
+const GRID_COLOR:Color = Color::srgba(1.0, 1.0, 1.0, 0.2);
+const HELP:&str = "LMB select and drag, RMB look, wheel zoom, WASD QE fly (shift faster)\n\
+                   1 wall, 2 cheese, 3 gate with goal, 4 platform, R rotate (shift back), Del delete\n\
+                   G snap, Ctrl+Z undo, Ctrl+Y redo, Ctrl+S save";
+
+/// What the number keys put where the cursor points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeable {
+    Wall,
+    Cheese,
+    Gate,
+    Platform
+}
+
+const PLACE_KEYS:[(KeyCode, Placeable); 4] = [
+    (KeyCode::Digit1, Placeable::Wall),
+    (KeyCode::Digit2, Placeable::Cheese),
+    (KeyCode::Digit3, Placeable::Gate),
+    (KeyCode::Digit4, Placeable::Platform),
+];
+
+/// Selection, undo history and settings of the editor, kept while the same level is edited.
+#[derive(Resource)]
+pub struct Editor {
+    /// the level being edited
+    pub path:String,
+    pub selected:Option<LevelElement>,
+    /// grid positions snap to, free placement when not set
+    pub snap:Option<f32>,
+    /// changed since the last save
+    pub unsaved:bool,
+    undo:Vec<Level>,
+    redo:Vec<Level>,
+    drag:Option<Drag>,
+    /// the level entities no longer match the level
+    rebuild:bool,
+    /// the level as last edited, while a save has not been reloaded by the file watcher
+    saved_reload:Option<Level>,
+    /// changes of the level asset made by the editor, their events are not reloads
+    own_changes:usize
+}
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            selected: None,
+            snap: Some(DEFAULT_SNAP),
+            unsaved: false,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            drag: None,
+            rebuild: false,
+            saved_reload: None,
+            own_changes: 0
+        }
+    }
+}
+impl Editor {
+    /// Remembers `before` as the level to go back to on undo.
+    fn record(&mut self, before: Level) {
+        self.undo.push(before);
+        if self.undo.len() > HISTORY_LENGTH {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.unsaved = true;
+    }
+
+    /// Notes that the editor changed the level asset to `level`.
+    fn changed(&mut self, level: &Level) {
+        self.own_changes += 1;
+        if let Some(latest) = &mut self.saved_reload {
+            *latest = level.clone();
+        }
+    }
+
+    fn snap(&self, position: Vec3) -> Vec3 {
+        match self.snap {
+            Some(grid) => Vec3::new((position.x / grid).round() * grid,
+                                    position.y,
+                                    (position.z / grid).round() * grid),
+            None => position
+        }
+    }
+}
+
+/// An element following the cursor while the left button is held.
+struct Drag {
+    element:LevelElement,
+    /// the level before the drag, for undo
+    before:Level,
+    /// height of the plane the cursor moves the element in
+    height:f32,
+    /// from the point that was grabbed to the element
+    offset:Vec3,
+    moved:bool
+}
+
+/// The free camera, orbiting `focus`.
+#[derive(Resource, Default)]
+struct EditorCamera {
+    focus:Vec3,
+    yaw:f32,
+    pitch:f32,
+    distance:f32
+}
+
+#[derive(Component)]
+struct EditorStatus;
+
+/// The folder the asset server reads levels from, so they are saved where they were loaded.
+#[derive(Resource)]
+struct AssetFolder(PathBuf);
+
+/// Picks, moves, places and deletes level elements and saves the level file.
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        let folder = app.get_added_plugins::<AssetPlugin>().first()
+            .map(|plugin| plugin.file_path.clone())
+            .unwrap_or_else(|| AssetPlugin::default().file_path);
+        app
+            .insert_resource(AssetFolder(FileAssetReader::get_base_path().join(folder)))
+            .init_resource::<Editor>()
+            .init_resource::<EditorCamera>()
+            .add_systems(Update,toggle_editor)
+            .add_systems(Update,keep_edits_on_reload
+                .before(level::reload_level)
+                .run_if(resource_exists::<CurrentLevel>))
+            .add_systems(OnEnter(GameState::Editor),enter_editor)
+            .add_systems(OnExit(GameState::Editor),exit_editor)
+            .add_systems(Update,(fly_camera,
+                                 drag,
+                                 edit,
+                                 rebuild_level,
+                                 draw_gizmos,
+                                 update_status).chain()
+                .run_if(in_state(GameState::Editor)));
+    }
+}
+
+/// The point under the mouse cursor and the level element there.
+#[derive(SystemParam)]
+struct Cursor<'w, 's> {
+    rapier_context:ReadDefaultRapierContext<'w, 's>,
+    query_window:Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    query_camera:Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<ThirdPersonCamera>>,
+    query_elements:Query<'w, 's, &'static LevelElement>,
+    query_parents:Query<'w, 's, &'static Parent>
+}
+impl Cursor<'_, '_> {
+    fn ray(&self) -> Option<Ray3d> {
+        let cursor = self.query_window.get_single().ok()?.cursor_position()?;
+        let (camera, camera_transform) = self.query_camera.get_single().ok()?;
+        camera.viewport_to_world(camera_transform, cursor).ok()
+    }
+
+    /// Where the ray hits a collider and the element the collider belongs to, if any.
+    fn hit(&self, ray: Ray3d) -> Option<(Vec3, Option<LevelElement>)> {
+        let (entity, distance) = self.rapier_context.single()
+            .cast_ray(ray.origin, *ray.direction, PICK_DISTANCE, true, QueryFilter::default())?;
+        //colliders of walls in gates and of cheese models are children of the tagged entity
+        let element = std::iter::once(entity)
+            .chain(self.query_parents.iter_ancestors(entity))
+            .find_map(|entity| self.query_elements.get(entity).ok().copied());
+        Some((ray.get_point(distance), element))
+    }
+}
+
+fn toggle_editor(
+    player_input: Res<PlayerInput>,
+    endless: Res<EndlessRun>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    //an endless course has no file to edit
+    if !player_input.editor || endless.active {
+        return;
+    }
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Editor),
+        GameState::Editor => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+fn enter_editor(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_level: Res<CurrentLevel>,
+    mut editor: ResMut<Editor>,
+    mut editor_camera: ResMut<EditorCamera>,
+    query_camera: Query<&Transform, With<ThirdPersonCamera>>,
+){
+    if editor.path != current_level.path {
+        *editor = Editor {
+            path: current_level.path.clone(),
+            snap: editor.snap,
+            ..default()
+        };
+    }
+    editor.rebuild = true;
+
+    //start flying where the play camera is
+    if let Ok(transform) = query_camera.get_single() {
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let distance = 10.0;
+        *editor_camera = EditorCamera {
+            focus: transform.translation + transform.forward() * distance,
+            yaw,
+            pitch,
+            distance
+        };
+    }
+
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(Color::srgb(0.8, 0.8, 0.8)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        },
+        StateScoped(GameState::Editor)
+    ))
+        .insert(EditorStatus);
+}
+
+/// Leaves the level to be spawned again from the edited asset, like a restart.
+fn exit_editor(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut score: ResMut<Score>,
+    query_level: Query<Entity, With<LevelEntity>>,
+){
+    if let Some(drag) = editor.drag.take() {
+        if drag.moved {
+            editor.record(drag.before);
+        }
+    }
+    for entity in query_level.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    score.cheese = current_level.cheese_at_start;
+    current_level.spawned = false;
+    current_level.building = false;
+    current_level.keep_ball = false;
+}
+
+fn fly_camera(
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    mut editor_camera: ResMut<EditorCamera>,
+    mut query_camera: Query<&mut Transform, With<ThirdPersonCamera>>,
+){
+    if mouse_buttons.pressed(MouseButton::Right) {
+        editor_camera.yaw -= mouse_motion.delta.x * LOOK_SPEED;
+        editor_camera.pitch = (editor_camera.pitch - mouse_motion.delta.y * LOOK_SPEED)
+            .clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+    }
+    let scroll = match mouse_scroll.unit {
+        MouseScrollUnit::Line => mouse_scroll.delta.y,
+        MouseScrollUnit::Pixel => mouse_scroll.delta.y / 100.0
+    };
+    editor_camera.distance = (editor_camera.distance * (1.0 - scroll * 0.1)).clamp(1.0, 100.0);
+
+    //ctrl is for the editing shortcuts
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let heading = Quat::from_rotation_y(editor_camera.yaw);
+    let mut movement = Vec3::ZERO;
+    if !ctrl {
+        for (key, direction) in [(KeyCode::KeyW, Vec3::NEG_Z),
+                                 (KeyCode::KeyS, Vec3::Z),
+                                 (KeyCode::KeyA, Vec3::NEG_X),
+                                 (KeyCode::KeyD, Vec3::X),
+                                 (KeyCode::KeyQ, Vec3::NEG_Y),
+                                 (KeyCode::KeyE, Vec3::Y)] {
+            if keyboard_input.pressed(key) {
+                movement += heading * direction;
+            }
+        }
+    }
+    let speed = if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        CAMERA_SPEED * 3.0
+    } else {
+        CAMERA_SPEED
+    };
+    editor_camera.focus += movement.normalize_or_zero() * speed * time.delta_secs();
+
+    let rotation = Quat::from_euler(EulerRot::YXZ, editor_camera.yaw, editor_camera.pitch, 0.0);
+    for mut transform in query_camera.iter_mut() {
+        transform.rotation = rotation;
+        transform.translation = editor_camera.focus + rotation * Vec3::Z * editor_camera.distance;
+    }
+}
+
+/// Selects what is clicked and moves it with the cursor while the button is held.
+fn drag(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    cursor: Cursor,
+    current_level: Res<CurrentLevel>,
+    mut levels: ResMut<Assets<Level>>,
+    mut editor: ResMut<Editor>,
+    mut query_transforms: Query<(&LevelElement, &mut Transform)>,
+){
+    if mouse_buttons.just_released(MouseButton::Left) {
+        if let Some(drag) = editor.drag.take() {
+            if drag.moved {
+                editor.record(drag.before);
+            }
+        }
+    }
+    let Some(ray) = cursor.ray() else {
+        return;
+    };
+
+    if mouse_buttons.just_pressed(MouseButton::Left) {
+        let Some(level) = levels.get(&current_level.handle) else {
+            return;
+        };
+        let hit = cursor.hit(ray);
+        editor.selected = hit.and_then(|(_, element)| element);
+        editor.drag = hit.and_then(|(point, element)| {
+            let element = element?;
+            Some(Drag {
+                element,
+                before: level.clone(),
+                height: point.y,
+                offset: world_transform(level, element)?.translation - point,
+                moved: false
+            })
+        });
+        return;
+    }
+
+    let Some(drag) = &editor.drag else {
+        return;
+    };
+    let Some(distance) = ray.intersect_plane(Vec3::new(0.0, drag.height, 0.0), InfinitePlane3d::new(Vec3::Y)) else {
+        return;
+    };
+    let element = drag.element;
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+    let (Some(current), Some(world)) = (element_transform(level, element).map(|transform| transform.translation),
+                                        to_local(level, parent_platform(element), ray.get_point(distance) + drag.offset)) else {
+        return;
+    };
+    //only slide, the height stays
+    let position = editor.snap(Vec3::new(world.x, current.y, world.z));
+    if position == current {
+        return;
+    }
+
+    let Some(level) = levels.get_mut(&current_level.handle) else {
+        return;
+    };
+    if let Some((translation, _)) = element_mut(level, element) {
+        *translation = position;
+    }
+    editor.changed(level);
+    //move the entity instead of rebuilding the level every frame
+    let transform = element_transform(level, element);
+    for (entity_element, mut entity_transform) in query_transforms.iter_mut() {
+        if *entity_element == element {
+            if let Some(transform) = transform {
+                *entity_transform = transform;
+            }
+        }
+    }
+    if let Some(drag) = &mut editor.drag {
+        drag.moved = true;
+    }
+}
+
+/// The keyboard commands of the editor.
+fn edit(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    cursor: Cursor,
+    current_level: Res<CurrentLevel>,
+    asset_folder: Res<AssetFolder>,
+    mut levels: ResMut<Assets<Level>>,
+    mut editor: ResMut<Editor>,
+){
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+    if editor.drag.is_some() {
+        return;
+    }
+
+    if ctrl {
+        if keyboard_input.just_pressed(KeyCode::KeyS) && save_level(level, &asset_folder.0.join(&current_level.path)) {
+            editor.unsaved = false;
+            if cfg!(feature = "hot_reload") {
+                editor.saved_reload = Some(level.clone());
+            }
+        }
+        let undo = keyboard_input.just_pressed(KeyCode::KeyZ) && !shift;
+        let redo = keyboard_input.just_pressed(KeyCode::KeyY)
+            || (keyboard_input.just_pressed(KeyCode::KeyZ) && shift);
+        let restored = if undo {
+            editor.undo.pop().inspect(|_| editor.redo.push(level.clone()))
+        } else if redo {
+            editor.redo.pop().inspect(|_| editor.undo.push(level.clone()))
+        } else {
+            None
+        };
+        if let Some(restored) = restored {
+            editor.changed(&restored);
+            levels.insert(&current_level.handle, restored);
+            editor.selected = None;
+            editor.unsaved = true;
+            editor.rebuild = true;
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyG) {
+        editor.snap = match editor.snap {
+            Some(_) => None,
+            None => Some(DEFAULT_SNAP)
+        };
+    }
+
+    let delete = keyboard_input.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]);
+    let rotate = keyboard_input.just_pressed(KeyCode::KeyR);
+    let place = PLACE_KEYS.iter()
+        .find(|(key, _)| keyboard_input.just_pressed(*key))
+        .map(|(_, placeable)| *placeable);
+    if !(delete || rotate || place.is_some()) {
+        return;
+    }
+
+    let mut edited = level.clone();
+    let changed = match (place, editor.selected) {
+        (Some(placeable), _) => {
+            let placed = cursor.ray().and_then(|ray| place_element(&mut edited, placeable, ray, cursor.hit(ray), &editor));
+            if placed.is_some() {
+                editor.selected = placed;
+            }
+            placed.is_some()
+        }
+        (None, Some(selected)) if delete => {
+            editor.selected = None;
+            remove_element(&mut edited, selected)
+        }
+        (None, Some(selected)) => {
+            let step = if shift { -ROTATE_STEP } else { ROTATE_STEP };
+            match element_mut(&mut edited, selected) {
+                Some((_, Some(rotation))) => {
+                    rotation.y = (rotation.y + step).rem_euclid(360.0);
+                    true
+                }
+                _ => false
+            }
+        }
+        (None, None) => false
+    };
+    if changed {
+        editor.record(level.clone());
+        editor.rebuild = true;
+        editor.changed(&edited);
+        levels.insert(&current_level.handle, edited);
+    }
+}
+
+/// Puts the edited level back when the file watcher reloads a save over it.
+fn keep_edits_on_reload(
+    mut level_events: EventReader<AssetEvent<Level>>,
+    current_level: Res<CurrentLevel>,
+    mut levels: ResMut<Assets<Level>>,
+    mut editor: ResMut<Editor>,
+){
+    for event in level_events.read() {
+        if !event.is_modified(&current_level.handle) {
+            continue;
+        }
+        if editor.own_changes > 0 {
+            editor.own_changes -= 1;
+            continue;
+        }
+        let Some(latest) = editor.saved_reload.take() else {
+            continue;
+        };
+        editor.own_changes += 1;
+        editor.rebuild = true;
+        levels.insert(&current_level.handle, latest);
+    }
+}
+
+fn rebuild_level(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut cached_assets: CachedAssets,
+    levels: Res<Assets<Level>>,
+    current_level: Res<CurrentLevel>,
+    mut editor: ResMut<Editor>,
+    query_level: Query<Entity, With<LevelEntity>>,
+){
+    if !editor.rebuild {
+        return;
+    }
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+    editor.rebuild = false;
+    for entity in query_level.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    level::spawn_level_entities(&mut commands, &asset_server, &mut cached_assets, level);
+
+    //the ball's start, a sensor so it can be picked
+    commands.spawn((
+        Mesh3d(cached_assets.sphere(0.5)),
+        MeshMaterial3d(cached_assets.material(BALL_COLOR, false)),
+        Transform::from_translation(level.ball_start)
+    ))
+        .insert(Collider::ball(0.5))
+        .insert(Sensor)
+        .insert(LevelElement::BallStart)
+        .insert(LevelEntity);
+}
+
+fn draw_gizmos(
+    mut gizmos: Gizmos,
+    editor: Res<Editor>,
+    levels: Res<Assets<Level>>,
+    current_level: Res<CurrentLevel>,
+){
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+    //goals are invisible in play
+    for (index, platform) in level.platforms.iter().enumerate() {
+        if platform.goal.is_some() {
+            draw_box(&mut gizmos, level, LevelElement::Goal(index), GOAL_COLOR);
+        }
+        for (gate_index, gate) in platform.gates.iter().enumerate() {
+            if gate.goal {
+                draw_box(&mut gizmos, level, LevelElement::Gate(index, gate_index), GOAL_COLOR);
+            }
+        }
+    }
+
+    let Some(selected) = editor.selected else {
+        return;
+    };
+    draw_box(&mut gizmos, level, selected, SELECTED_COLOR);
+    //the grid the selection snaps to, lying in its platform
+    if let (Some(grid), Some(transform)) = (editor.snap, world_transform(level, selected)) {
+        let platform_rotation = parent_platform(selected)
+            .and_then(|platform| platform_transform(level, platform))
+            .map(|platform| platform.rotation)
+            .unwrap_or_default();
+        gizmos.grid(Isometry3d::new(transform.translation, platform_rotation * Quat::from_rotation_x(FRAC_PI_2)),
+                    UVec2::splat(20),
+                    Vec2::splat(grid),
+                    GRID_COLOR);
+    }
+}
+
+fn draw_box(
+    gizmos: &mut Gizmos,
+    level: &Level,
+    element: LevelElement,
+    color: Color
+){
+    let (Some(transform), Some((center, size))) = (world_transform(level, element), element_box(level, element)) else {
+        return;
+    };
+    gizmos.cuboid(transform * Transform::from_translation(center).with_scale(size), color);
+}
+
+fn update_status(
+    editor: Res<Editor>,
+    mut query_status: Query<&mut Text, With<EditorStatus>>,
+){
+    let Ok(mut text) = query_status.get_single_mut() else {
+        return;
+    };
+    let selected = match editor.selected {
+        Some(element) => format!("{:?}", element),
+        None => "nothing".to_string()
+    };
+    let snap = match editor.snap {
+        Some(grid) => format!("{}", grid),
+        None => "off".to_string()
+    };
+    text.0 = format!("{}{}  selected: {}  snap: {}  undo: {}\n{}",
+                     editor.path,
+                     if editor.unsaved { " (unsaved)" } else { "" },
+                     selected, snap, editor.undo.len(), HELP);
+}
+
+/// Writes the level back to its file in the assets folder.
+fn save_level(level: &Level, file: &Path) -> bool {
+    match level.save(file) {
+        Ok(()) => {
+            info!("saved {}", file.display());
+            true
+        }
+        Err(error) => {
+            warn!("{}: {}", file.display(), error);
+            false
+        }
+    }
+}
+
+/// Adds a new element where the cursor points, walls and gates go onto the platform there.
+fn place_element(
+    level: &mut Level,
+    placeable: Placeable,
+    ray: Ray3d,
+    hit: Option<(Vec3, Option<LevelElement>)>,
+    editor: &Editor
+) -> Option<LevelElement> {
+    let platform = hit.and_then(|(_, element)| match element? {
+        LevelElement::Platform(index) => Some(index),
+        element => parent_platform(element)
+    });
+    match placeable {
+        Placeable::Wall | Placeable::Gate => {
+            let index = platform?;
+            let mut position = editor.snap(to_local(level, Some(index), hit?.0)?);
+            let platform = &mut level.platforms[index];
+            let top = platform.size.y / 2.0;
+            if placeable == Placeable::Wall {
+                let size = Vec3::new(2.0, 0.4, 0.4);
+                position.y = top + size.y / 2.0;
+                platform.walls.push(BlockDef {
+                    size,
+                    position,
+                    rotation: Vec3::ZERO,
+                    color: None
+                });
+                Some(LevelElement::Wall(index, platform.walls.len() - 1))
+            } else {
+                position.y = top;
+                platform.gates.push(GateDef {
+                    goal: true,
+                    ..GateDef::new(position, 2.0, 1.4)
+                });
+                Some(LevelElement::Gate(index, platform.gates.len() - 1))
+            }
+        }
+        Placeable::Cheese => {
+            let position = editor.snap(hit?.0 + Vec3::Y * 0.3);
+            level.cheese.push(position);
+            Some(LevelElement::Cheese(level.cheese.len() - 1))
+        }
+        Placeable::Platform => {
+            //continue at the height of the last platform
+            let height = level.platforms.last().map(|platform| platform.position.y).unwrap_or(0.0);
+            let distance = ray.intersect_plane(Vec3::new(0.0, height, 0.0), InfinitePlane3d::new(Vec3::Y))?;
+            let position = editor.snap(ray.get_point(distance));
+            level.platforms.push(PlatformDef::new(Vec3::new(4.0, 0.1, 4.0), position));
+            Some(LevelElement::Platform(level.platforms.len() - 1))
+        }
+    }
+}
+
+fn remove_element(level: &mut Level, element: LevelElement) -> bool {
+    fn remove_at<T>(list: &mut Vec<T>, index: usize) -> bool {
+        if index < list.len() {
+            list.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+    match element {
+        LevelElement::Platform(index) => remove_at(&mut level.platforms, index),
+        LevelElement::Wall(platform, index) => level.platforms.get_mut(platform)
+            .is_some_and(|platform| remove_at(&mut platform.walls, index)),
+        LevelElement::Door(platform, index) => level.platforms.get_mut(platform)
+            .is_some_and(|platform| remove_at(&mut platform.doors, index)),
+        LevelElement::Gate(platform, index) => level.platforms.get_mut(platform)
+            .is_some_and(|platform| remove_at(&mut platform.gates, index)),
+        LevelElement::Goal(platform) => level.platforms.get_mut(platform)
+            .is_some_and(|platform| platform.goal.take().is_some()),
+        LevelElement::Cheese(index) => remove_at(&mut level.cheese, index),
+        //the ball has to start somewhere
+        LevelElement::BallStart => false
+    }
+}
+
+/// The position of an element in the level data and its rotation if it has one.
+fn element_mut(level: &mut Level, element: LevelElement) -> Option<(&mut Vec3, Option<&mut Vec3>)> {
+    match element {
+        LevelElement::Platform(index) => {
+            let platform = level.platforms.get_mut(index)?;
+            Some((&mut platform.position, Some(&mut platform.rotation)))
+        }
+        LevelElement::Wall(platform, index) => {
+            let wall = level.platforms.get_mut(platform)?.walls.get_mut(index)?;
+            Some((&mut wall.position, Some(&mut wall.rotation)))
+        }
+        LevelElement::Door(platform, index) => {
+            let door = level.platforms.get_mut(platform)?.doors.get_mut(index)?;
+            Some((&mut door.position, Some(&mut door.rotation)))
+        }
+        LevelElement::Gate(platform, index) => {
+            let gate = level.platforms.get_mut(platform)?.gates.get_mut(index)?;
+            Some((&mut gate.position, Some(&mut gate.rotation)))
+        }
+        LevelElement::Goal(platform) => {
+            let goal = level.platforms.get_mut(platform)?.goal.as_mut()?;
+            Some((&mut goal.position, None))
+        }
+        LevelElement::Cheese(index) => Some((level.cheese.get_mut(index)?, None)),
+        LevelElement::BallStart => Some((&mut level.ball_start, None))
+    }
+}
+
+/// The transform of the element's entity, relative to its platform for walls, doors, gates and goals.
+fn element_transform(level: &Level, element: LevelElement) -> Option<Transform> {
+    let (position, degrees) = match element {
+        LevelElement::Platform(index) => {
+            let platform = level.platforms.get(index)?;
+            (platform.position, platform.rotation)
+        }
+        LevelElement::Wall(platform, index) => {
+            let wall = level.platforms.get(platform)?.walls.get(index)?;
+            (wall.position, wall.rotation)
+        }
+        LevelElement::Door(platform, index) => {
+            let door = level.platforms.get(platform)?.doors.get(index)?;
+            (door.position, door.rotation)
+        }
+        LevelElement::Gate(platform, index) => {
+            let gate = level.platforms.get(platform)?.gates.get(index)?;
+            (gate.position, gate.rotation)
+        }
+        LevelElement::Goal(platform) => (level.platforms.get(platform)?.goal.as_ref()?.position, Vec3::ZERO),
+        LevelElement::Cheese(index) => (*level.cheese.get(index)?, Vec3::ZERO),
+        LevelElement::BallStart => (level.ball_start, Vec3::ZERO)
+    };
+    Some(Transform {
+        translation: position,
+        rotation: prefab::rotation(degrees),
+        ..default()
+    })
+}
+
+/// Center and size of the box outlining an element, in the element's space.
+fn element_box(level: &Level, element: LevelElement) -> Option<(Vec3, Vec3)> {
+    match element {
+        LevelElement::Platform(index) => Some((Vec3::ZERO, level.platforms.get(index)?.size)),
+        LevelElement::Wall(platform, index) => Some((Vec3::ZERO, level.platforms.get(platform)?.walls.get(index)?.size)),
+        LevelElement::Door(platform, index) => Some((Vec3::ZERO, level.platforms.get(platform)?.doors.get(index)?.size)),
+        LevelElement::Gate(platform, index) => {
+            let gate = level.platforms.get(platform)?.gates.get(index)?;
+            Some((Vec3::Y * gate.height / 2.0, Vec3::new(gate.width, gate.height, gate.thickness)))
+        }
+        LevelElement::Goal(platform) => Some((Vec3::ZERO, level.platforms.get(platform)?.goal.as_ref()?.size)),
+        LevelElement::Cheese(_) => Some((Vec3::ZERO, Vec3::splat(0.5))),
+        LevelElement::BallStart => Some((Vec3::ZERO, Vec3::ONE))
+    }
+}
+
+/// The platform whose space the element's position is in.
+fn parent_platform(element: LevelElement) -> Option<usize> {
+    match element {
+        LevelElement::Wall(platform, _)
+        | LevelElement::Door(platform, _)
+        | LevelElement::Gate(platform, _)
+        | LevelElement::Goal(platform) => Some(platform),
+        LevelElement::Platform(_) | LevelElement::Cheese(_) | LevelElement::BallStart => None
+    }
+}
+
+fn platform_transform(level: &Level, index: usize) -> Option<Transform> {
+    let platform = level.platforms.get(index)?;
+    Some(Transform {
+        translation: platform.position,
+        rotation: prefab::rotation(platform.rotation),
+        ..default()
+    })
+}
+
+fn world_transform(level: &Level, element: LevelElement) -> Option<Transform> {
+    let transform = element_transform(level, element)?;
+    match parent_platform(element) {
+        Some(platform) => Some(platform_transform(level, platform)?.mul_transform(transform)),
+        None => Some(transform)
+    }
+}
+
+/// `world` in the space of `platform`, or unchanged without one.
+fn to_local(level: &Level, platform: Option<usize>, world: Vec3) -> Option<Vec3> {
+    match platform {
+        Some(platform) => {
+            let transform = platform_transform(level, platform)?;
+            Some(transform.rotation.inverse() * (world - transform.translation))
+        }
+        None => Some(world)
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::level::{CurrentLevel, LevelCompleteEvent, Wall};
+use crate::particles::{Particle, ParticleLook, ParticlePlugin, ParticleShape, Particles};
+use crate::rng::{GameRng, RngStream};
+use crate::score;
+use crate::Ball;
+
+const PRESETS:&str = "effects/default.effects.ron";
+/// the ball has to hit a wall at least this fast to make sparks
+const IMPACT_SPEED:f32 = 2.0;
+
+/// Shows the effect `preset` at `position`.
+#[derive(Event)]
+pub struct CreateEffectEvent {
+    pub preset:String,
+    pub position:Vec3
+}
+impl CreateEffectEvent {
+    pub fn new(preset: &str, position: Vec3) -> Self {
+        Self {
+            preset: preset.to_string(),
+            position
+        }
+    }
+}
+
+/// A burst of particles, the particles start around the position and fly off with some randomness.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectPreset {
+    #[serde(default)]
+    pub shape:ParticleShape,
+    pub count:u32,
+    pub size:f32,
+    /// particles start up to this far from the position
+    #[serde(default)]
+    pub radius:f32,
+    pub color_start:(f32, f32, f32),
+    pub color_end:(f32, f32, f32),
+    #[serde(default)]
+    pub emissive:f32,
+    pub velocity:Vec3,
+    /// random velocity added on top, per axis in -spread..spread
+    #[serde(default)]
+    pub spread:Vec3,
+    pub lifetime:f32,
+    #[serde(default = "default_gravity_scale")]
+    pub gravity_scale:f32,
+    #[serde(default)]
+    pub drag:f32
+}
+impl EffectPreset {
+    fn look(&self) -> ParticleLook {
+        ParticleLook {
+            shape: self.shape,
+            color_start: self.color_start,
+            color_end: self.color_end,
+            emissive: self.emissive
+        }
+    }
+}
+
+fn default_gravity_scale() -> f32 {
+    1.0
+}
+
+/// The effect presets by name, loaded from an `.effects.ron` file.
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct EffectPresets {
+    pub presets:HashMap<String, EffectPreset>
+}
+
+#[derive(Default)]
+pub struct EffectPresetsLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum EffectPresetsLoaderError {
+    #[error("Could not load effects: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse effects: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for EffectPresetsLoader {
+    type Asset = EffectPresets;
+    type Settings = ();
+    type Error = EffectPresetsLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let presets = ron::de::from_bytes::<EffectPresets>(&bytes)?;
+        Ok(presets)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["effects.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct EffectPresetsHandle(Handle<EffectPresets>);
+
+/// Particle bursts for cheese pickups, wall impacts, respawns and finished levels.
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_plugins(ParticlePlugin)
+            .init_asset::<EffectPresets>()
+            .init_asset_loader::<EffectPresetsLoader>()
+            .add_event::<CreateEffectEvent>()
+            .add_systems(Startup,setup)
+            .add_systems(Update,reload_presets)
+            .add_systems(FixedUpdate,(wall_impact,
+                                      level_complete_effect,
+                                      create_effect).chain()
+                .after(score::collision)
+                .after(score::fall_off));
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.insert_resource(EffectPresetsHandle(asset_server.load(PRESETS)));
+}
+
+/// Changed presets get new materials the next time they are used.
+fn reload_presets(
+    mut asset_events: EventReader<AssetEvent<EffectPresets>>,
+    mut particles: Particles,
+){
+    for event in asset_events.read() {
+        if let AssetEvent::Modified { .. } = event {
+            particles.clear_looks();
+        }
+    }
+}
+
+fn create_effect(
+    mut event_create_effect: EventReader<CreateEffectEvent>,
+    presets_handle: Res<EffectPresetsHandle>,
+    presets: Res<Assets<EffectPresets>>,
+    mut particles: Particles,
+    mut game_rng: ResMut<GameRng>,
+)
+{
+    let Some(presets) = presets.get(&presets_handle.0) else {
+        event_create_effect.clear();
+        return;
+    };
+    let rng = game_rng.stream(RngStream::Effects);
+    for event in event_create_effect.read() {
+        let Some(preset) = presets.presets.get(&event.preset) else {
+            warn!("no effect preset called {}", event.preset);
+            continue;
+        };
+        let look = particles.look(&event.preset, &preset.look());
+        for _ in 0..preset.count {
+            let offset = Vec3::new(rng.gen_range(-1.0..=1.0),
+                                   rng.gen_range(0.0..=1.0),
+                                   rng.gen_range(-1.0..=1.0)) * preset.radius;
+            let spread = Vec3::new(rng.gen_range(-1.0..=1.0),
+                                   rng.gen_range(-1.0..=1.0),
+                                   rng.gen_range(-1.0..=1.0)) * preset.spread;
+            particles.emit(event.position + offset, look, preset.lifetime, Particle {
+                velocity: preset.velocity + spread,
+                drag: preset.drag,
+                gravity_scale: preset.gravity_scale,
+                ground: event.position.y,
+                size: preset.size,
+                ..default()
+            });
+        }
+    }
+}
+
+fn wall_impact(
+    mut collision_events: EventReader<CollisionEvent>,
+    query_ball: Query<(&Transform, &Velocity), With<Ball>>,
+    query_walls: Query<(), With<Wall>>,
+    mut event_create_effect: EventWriter<CreateEffectEvent>,
+){
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(a, b, _) = collision_event else {
+            continue;
+        };
+        for (ball, other) in [(a, b), (b, a)] {
+            let Ok((transform, velocity)) = query_ball.get(*ball) else {
+                continue;
+            };
+            if query_walls.contains(*other) && velocity.linvel.length() >= IMPACT_SPEED {
+                event_create_effect.send(CreateEffectEvent::new("impact", transform.translation));
+            }
+        }
+    }
+}
+
+fn level_complete_effect(
+    mut event_level_complete: EventReader<LevelCompleteEvent>,
+    current_level: Res<CurrentLevel>,
+    query_ball: Query<&Transform, With<Ball>>,
+    mut event_create_effect: EventWriter<CreateEffectEvent>,
+){
+    if event_level_complete.is_empty() {
+        return;
+    }
+    event_level_complete.clear();
+    let position = query_ball.get_single()
+        .map(|transform| transform.translation)
+        .unwrap_or(current_level.ball_start);
+    event_create_effect.send(CreateEffectEvent::new("level_complete", position));
+}
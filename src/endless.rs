@@ -0,0 +1,259 @@
+//! An endless run: chunks from the `CourseGenerator` are streamed in ahead of the ball and
+//! removed behind it, getting harder the further the ball gets.
+//!
+//! Chunks are generated on the async compute pool and their pieces spawned a few per frame,
+//! so neither the generator's solvability check nor spawning stalls a frame.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::asset_cache::CachedAssets;
+use crate::generator::{Chunk, CourseGenerator, GeneratorError};
+use crate::input::PlayerInput;
+use crate::level::{CurrentLevel, Level, LevelEntity, LevelStartedEvent, PlatformDef};
+use crate::prefab;
+use crate::replay;
+use crate::rng::{GameRng, RngStream};
+use crate::score::Score;
+use crate::state::GameState;
+use crate::Ball;
+
+/// what `CurrentLevel` is called during a run
+pub const ENDLESS_LEVEL:&str = "endless";
+/// chunks kept ready ahead of the ball
+const CHUNKS_AHEAD:usize = 3;
+const CHUNK_SEGMENTS:usize = 3;
+/// chunks ending further behind the ball are removed
+const DESPAWN_BEHIND:f32 = 20.0;
+const START_DIFFICULTY:f32 = 0.1;
+/// distance over which the difficulty rises from `START_DIFFICULTY` to the hardest
+const RAMP_DISTANCE:f32 = 600.0;
+/// platforms and cheese spawned per frame
+const PIECES_PER_FRAME:usize = 2;
+/// how far below the lowest platform around the ball it is lost
+const FALL_HEIGHT:f32 = 10.0;
+
+/// State of the endless run, `active` while one is played.
+#[derive(Resource, Default)]
+pub struct EndlessRun {
+    pub active:bool,
+    /// numbers for the chunks, away while a chunk is generated with it
+    rng:Option<StdRng>,
+    task:Option<Task<(Result<Chunk, GeneratorError>, StdRng)>>,
+    /// where the next chunk continues the course
+    next_start:Vec3,
+    next_index:usize,
+    /// chunks with their pieces spawned or waiting to be, oldest first
+    chunks:VecDeque<StreamedChunk>,
+    pending:VecDeque<(usize, Piece)>
+}
+
+struct StreamedChunk {
+    index:usize,
+    start:Vec3,
+    end:Vec3,
+    /// where the ball comes back after falling off in this chunk
+    checkpoint:Vec3,
+    /// top of the lowest platform
+    lowest:f32
+}
+
+enum Piece {
+    Platform(PlatformDef),
+    Cheese(Vec3)
+}
+
+/// Marks the entities spawned for a chunk.
+#[derive(Component)]
+struct ChunkEntity(usize);
+
+/// Endless runs started from the menu.
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<EndlessRun>()
+            .add_systems(Update,start_endless.run_if(in_state(GameState::MainMenu)))
+            .add_systems(OnEnter(GameState::MainMenu),end_run)
+            .add_systems(Update,begin_run.after(replay::begin_replay))
+            .add_systems(Update,(generate_chunks,
+                                 spawn_pieces,
+                                 follow_ball).chain()
+                .after(begin_run)
+                .run_if(in_state(GameState::Playing))
+                .run_if(|endless: Res<EndlessRun>| endless.active));
+    }
+}
+
+fn start_endless(
+    player_input: Res<PlayerInput>,
+    mut levels: ResMut<Assets<Level>>,
+    mut score: ResMut<Score>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if !player_input.endless {
+        return;
+    }
+    *score = Score::default();
+    //the run starts on a single platform, the chunks continue at its far edge
+    let level = Level {
+        ball_start: Vec3::new(0.0, 1.0, -2.0),
+        platforms: vec![PlatformDef::new(Vec3::new(6.0, 0.1, 6.0), Vec3::new(0.0, -0.05, -3.0))],
+        goal_requires_cheese: false,
+        ..default()
+    };
+    *current_level = CurrentLevel::new(ENDLESS_LEVEL, levels.add(level));
+    next_state.set(GameState::Playing);
+}
+
+/// Starts the chunk stream whenever the run's level is spawned, also after a restart.
+fn begin_run(
+    mut event_level_started: EventReader<LevelStartedEvent>,
+    mut game_rng: ResMut<GameRng>,
+    mut score: ResMut<Score>,
+    mut endless: ResMut<EndlessRun>,
+){
+    for event in event_level_started.read() {
+        if event.path != ENDLESS_LEVEL {
+            *endless = EndlessRun::default();
+            continue;
+        }
+        *endless = EndlessRun {
+            active: true,
+            rng: Some(StdRng::seed_from_u64(game_rng.stream(RngStream::Placement).gen())),
+            next_start: Vec3::new(0.0, 0.0, -6.0),
+            ..default()
+        };
+        score.distance = 0;
+    }
+}
+
+fn end_run(
+    mut endless: ResMut<EndlessRun>,
+){
+    //dropping the task cancels it
+    *endless = EndlessRun::default();
+}
+
+/// Generates one chunk at a time while fewer than `CHUNKS_AHEAD` are ahead of the ball.
+fn generate_chunks(
+    mut endless: ResMut<EndlessRun>,
+    query_ball: Query<&Transform, With<Ball>>,
+){
+    if let Some(task) = &mut endless.task {
+        let Some((result, rng)) = block_on(future::poll_once(task)) else {
+            return;
+        };
+        endless.task = None;
+        endless.rng = Some(rng);
+        match result {
+            Ok(chunk) => add_chunk(&mut endless, chunk),
+            //tried again next frame with the numbers that follow
+            Err(error) => warn!("endless chunk {}: {}", endless.next_index, error)
+        }
+    }
+
+    let Ok(ball) = query_ball.get_single() else {
+        return;
+    };
+    let ahead = endless.chunks.iter()
+        .filter(|chunk| chunk.end.z < ball.translation.z)
+        .count();
+    if ahead >= CHUNKS_AHEAD {
+        return;
+    }
+    let Some(mut rng) = endless.rng.take() else {
+        return;
+    };
+    let start = endless.next_start;
+    let generator = CourseGenerator {
+        difficulty: (START_DIFFICULTY - start.z / RAMP_DISTANCE).min(1.0),
+        segments: CHUNK_SEGMENTS
+    };
+    endless.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        let result = generator.chunk(&mut rng, start);
+        (result, rng)
+    }));
+}
+
+fn add_chunk(endless: &mut EndlessRun, chunk: Chunk) {
+    let index = endless.next_index;
+    let first = &chunk.platforms[0];
+    let first_top = first.position.y + first.size.y / 2.0;
+    endless.chunks.push_back(StreamedChunk {
+        index,
+        start: endless.next_start,
+        end: chunk.end,
+        checkpoint: Vec3::new(first.position.x, first_top + 1.0, first.position.z + first.size.z / 2.0 - 1.0),
+        lowest: chunk.platforms.iter()
+            .map(|platform| platform.position.y + platform.size.y / 2.0)
+            .fold(first_top, f32::min)
+    });
+    //platforms first, so the cheese has something to fall onto
+    endless.pending.extend(chunk.platforms.into_iter().map(|platform| (index, Piece::Platform(platform))));
+    endless.pending.extend(chunk.cheese.into_iter().map(|cheese| (index, Piece::Cheese(cheese))));
+    endless.next_start = chunk.end;
+    endless.next_index += 1;
+}
+
+fn spawn_pieces(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut cached_assets: CachedAssets,
+    mut endless: ResMut<EndlessRun>,
+){
+    for _ in 0..PIECES_PER_FRAME {
+        let Some((index, piece)) = endless.pending.pop_front() else {
+            return;
+        };
+        let entity = match piece {
+            Piece::Platform(platform) => prefab::spawn_platform(&mut commands, &mut cached_assets, &platform).platform,
+            Piece::Cheese(position) => prefab::spawn_cheese(&mut commands, &asset_server, position)
+        };
+        commands.entity(entity)
+            .insert(ChunkEntity(index))
+            .insert(LevelEntity);
+    }
+}
+
+/// Counts the distance, moves the respawn point and kill height along and removes chunks left behind.
+fn follow_ball(
+    mut commands: Commands,
+    mut endless: ResMut<EndlessRun>,
+    mut score: ResMut<Score>,
+    mut current_level: ResMut<CurrentLevel>,
+    query_ball: Query<&Transform, With<Ball>>,
+    query_chunks: Query<(Entity, &ChunkEntity)>,
+){
+    let Ok(ball) = query_ball.get_single() else {
+        return;
+    };
+    let position = ball.translation;
+    score.distance = score.distance.max(-position.z as i32);
+
+    //the chunk the ball is in
+    if let Some(chunk) = endless.chunks.iter().rev().find(|chunk| chunk.start.z >= position.z) {
+        current_level.ball_start = chunk.checkpoint;
+    }
+    let lowest = endless.chunks.iter()
+        .map(|chunk| chunk.lowest)
+        .fold(0.0, f32::min);
+    current_level.kill_height = lowest - FALL_HEIGHT;
+
+    while endless.chunks.front().is_some_and(|chunk| chunk.end.z > position.z + DESPAWN_BEHIND) {
+        let Some(chunk) = endless.chunks.pop_front() else {
+            break;
+        };
+        for (entity, chunk_entity) in query_chunks.iter() {
+            if chunk_entity.0 == chunk.index {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
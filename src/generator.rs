@@ -0,0 +1,317 @@
+//! Courses put together from random segments running along -Z, each checked with
+//! `navigation` before it is handed out. The same seed always gives the same course.
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use thiserror::Error;
+
+use crate::level::{BlockDef, GateDef, Level, PlatformDef};
+use crate::navigation::{self, NavGraph};
+
+/// courses thrown away before giving up
+const MAX_ATTEMPTS:usize = 32;
+const PLATFORM_THICKNESS:f32 = 0.1;
+const WALL_HEIGHT:f32 = 0.4;
+const WALL_THICKNESS:f32 = 0.4;
+/// cheese falls onto the platforms from this high
+const CHEESE_HEIGHT:f32 = 0.5;
+
+#[derive(Debug, Error)]
+pub enum GeneratorError {
+    #[error("no solvable course in {0} attempts")]
+    Unsolvable(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Segment {
+    /// a plain platform, with rails when easy
+    Straight,
+    /// walls across the platform, each leaving an opening on the other side
+    Slalom,
+    /// a lower platform beyond a gap
+    Gap,
+    /// a platform moved sideways
+    Shift,
+    /// a narrow platform
+    Bridge
+}
+
+/// A stretch of course. Its first platform continues where the stretch before it ended.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    pub platforms:Vec<PlatformDef>,
+    pub cheese:Vec<Vec3>,
+    /// middle of the far edge of the last platform's top
+    pub end:Vec3
+}
+
+/// Makes courses of `segments` pieces, harder as `difficulty` goes from 0 to 1.
+#[derive(Debug, Clone, Copy)]
+pub struct CourseGenerator {
+    pub difficulty:f32,
+    pub segments:usize
+}
+impl Default for CourseGenerator {
+    fn default() -> Self {
+        Self {
+            difficulty: 0.3,
+            segments: 8
+        }
+    }
+}
+
+impl CourseGenerator {
+    /// A course from a start platform through the segments to a gate with the goal.
+    pub fn course(&self, seed: u64) -> Result<Level, GeneratorError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..MAX_ATTEMPTS {
+            let level = self.build_course(&mut rng);
+            if navigation::solvable(&level) {
+                return Ok(level);
+            }
+        }
+        Err(GeneratorError::Unsolvable(MAX_ATTEMPTS))
+    }
+
+    /// A stretch of course continuing at `start`, the ball can eat all its cheese and leave it at its end.
+    pub fn chunk(&self, rng: &mut StdRng, start: Vec3) -> Result<Chunk, GeneratorError> {
+        for _ in 0..MAX_ATTEMPTS {
+            let chunk = self.build_chunk(rng, start);
+            if chunk_solvable(&chunk, start) {
+                return Ok(chunk);
+            }
+        }
+        Err(GeneratorError::Unsolvable(MAX_ATTEMPTS))
+    }
+
+    fn build_course(&self, rng: &mut StdRng) -> Level {
+        let start = PlatformDef::new(Vec3::new(6.0, PLATFORM_THICKNESS, 6.0),
+                                     Vec3::new(0.0, -PLATFORM_THICKNESS / 2.0, -3.0));
+        let chunk = self.build_chunk(rng, Vec3::new(0.0, 0.0, -6.0));
+        let mut finish = PlatformDef::new(Vec3::new(6.0, PLATFORM_THICKNESS, 6.0),
+                                          chunk.end + Vec3::new(0.0, -PLATFORM_THICKNESS / 2.0, -3.0));
+        finish.gates.push(GateDef {
+            goal: true,
+            ..GateDef::new(Vec3::new(0.0, PLATFORM_THICKNESS / 2.0, -1.5), 2.0, 1.4)
+        });
+
+        let mut platforms = vec![start];
+        platforms.extend(chunk.platforms);
+        platforms.push(finish);
+        let lowest = platforms.iter()
+            .map(|platform| platform.position.y)
+            .fold(0.0, f32::min);
+        Level {
+            ball_start: Vec3::new(0.0, 1.0, -2.0),
+            kill_height: lowest - 10.0,
+            platforms,
+            cheese: chunk.cheese,
+            ..default()
+        }
+    }
+
+    fn build_chunk(&self, rng: &mut StdRng, start: Vec3) -> Chunk {
+        let mut chunk = Chunk {
+            end: start,
+            ..default()
+        };
+        for _ in 0..self.segments {
+            self.add_segment(rng, &mut chunk);
+        }
+        chunk
+    }
+
+    fn add_segment(&self, rng: &mut StdRng, chunk: &mut Chunk) {
+        let difficulty = self.difficulty.clamp(0.0, 1.0);
+        let segment = pick_segment(rng, difficulty);
+        let width = lerp(6.0, 3.0, difficulty) * rng.gen_range(0.8..1.2);
+        let (mut gap, mut drop, mut shift) = (0.0, 0.0, 0.0);
+        let (width, length) = match segment {
+            Segment::Straight | Segment::Shift => (width, rng.gen_range(6.0..10.0)),
+            Segment::Slalom => (width, rng.gen_range(8.0..12.0)),
+            Segment::Gap => (width, rng.gen_range(4.0..8.0)),
+            Segment::Bridge => (lerp(2.5, 1.2, difficulty) * rng.gen_range(0.9..1.1), rng.gen_range(4.0..8.0))
+        };
+        match segment {
+            Segment::Gap => {
+                //the widest gaps of hard courses are more than the ball can fly, the check sorts them out
+                drop = rng.gen_range(0.3..1.5) * (0.5 + difficulty);
+                gap = lerp(0.4, 2.2, difficulty) * rng.gen_range(0.6..1.2);
+            }
+            Segment::Shift => {
+                shift = rng.gen_range(0.3..0.7) * width * if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+            }
+            _ => {}
+        }
+
+        let top = chunk.end.y - drop;
+        let mut platform = PlatformDef::new(Vec3::new(width, PLATFORM_THICKNESS, length),
+                                            Vec3::new(chunk.end.x + shift,
+                                                      top - PLATFORM_THICKNESS / 2.0,
+                                                      chunk.end.z - gap - length / 2.0));
+        let wall_y = PLATFORM_THICKNESS / 2.0 + WALL_HEIGHT / 2.0;
+        //places on the platform, relative to it
+        let mut cheese_spots:Vec<Vec2> = Vec::new();
+        match segment {
+            Segment::Straight => {
+                if rng.gen_bool((1.0 - difficulty as f64).clamp(0.0, 1.0)) {
+                    for side in [-1.0, 1.0] {
+                        platform.walls.push(wall(Vec3::new(WALL_THICKNESS, WALL_HEIGHT, length),
+                                                 Vec3::new(side * (width + WALL_THICKNESS) / 2.0, wall_y, 0.0)));
+                    }
+                }
+                for _ in 0..rng.gen_range(0..=2) {
+                    cheese_spots.push(Vec2::new(rng.gen_range(-0.3..0.3) * width,
+                                                rng.gen_range(-0.4..0.4) * length));
+                }
+            }
+            Segment::Slalom => {
+                let opening = lerp(2.5, 1.4, difficulty);
+                let count = rng.gen_range(1..=1 + (2.0 * difficulty).round() as usize);
+                let mut side = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+                for index in 0..count {
+                    let z = length / 2.0 - (index as f32 + 1.0) * length / (count as f32 + 1.0);
+                    //the wall runs from one edge to the opening at the other
+                    platform.walls.push(wall(Vec3::new(width - opening, WALL_HEIGHT, WALL_THICKNESS),
+                                             Vec3::new(side * opening / 2.0, wall_y, z)));
+                    if rng.gen_bool(0.5) {
+                        cheese_spots.push(Vec2::new(-side * (width - opening) / 2.0, z));
+                    }
+                    side = -side;
+                }
+            }
+            Segment::Gap | Segment::Shift | Segment::Bridge => {
+                if rng.gen_bool(0.6) {
+                    cheese_spots.push(Vec2::new(0.0, rng.gen_range(-0.3..0.3) * length));
+                }
+            }
+        }
+
+        for spot in cheese_spots {
+            chunk.cheese.push(platform.position + Vec3::new(spot.x, PLATFORM_THICKNESS / 2.0 + CHEESE_HEIGHT, spot.y));
+        }
+        chunk.end = Vec3::new(platform.position.x, top, platform.position.z - length / 2.0);
+        chunk.platforms.push(platform);
+    }
+}
+
+fn pick_segment(rng: &mut StdRng, difficulty: f32) -> Segment {
+    let weights = [
+        (Segment::Straight, 1.5 - difficulty),
+        (Segment::Slalom, 0.5 + difficulty),
+        (Segment::Gap, 0.3 + difficulty),
+        (Segment::Shift, 0.5),
+        (Segment::Bridge, difficulty)
+    ];
+    let total:f32 = weights.iter().map(|(_, weight)| weight).sum();
+    let mut pick = rng.gen_range(0.0..total);
+    for (segment, weight) in weights {
+        if pick < weight {
+            return segment;
+        }
+        pick -= weight;
+    }
+    Segment::Straight
+}
+
+/// Whether a ball coming in at `start` can eat all cheese of the chunk and get to its end.
+fn chunk_solvable(chunk: &Chunk, start: Vec3) -> bool {
+    //a pad standing in for the stretch before
+    let pad = PlatformDef::new(Vec3::new(2.0, PLATFORM_THICKNESS, 2.0),
+                               start + Vec3::new(0.0, -PLATFORM_THICKNESS / 2.0, 1.0));
+    let mut platforms = vec![pad];
+    platforms.extend(chunk.platforms.iter().cloned());
+    let level = Level {
+        ball_start: start + Vec3::new(0.0, 1.0, 1.0),
+        platforms,
+        ..default()
+    };
+    let reachable = NavGraph::new(&level).reachable(level.ball_start);
+    chunk.cheese.iter().all(|cheese| reachable.reaches(*cheese)) && reachable.reaches(chunk.end)
+}
+
+fn wall(size: Vec3, position: Vec3) -> BlockDef {
+    BlockDef {
+        size,
+        position,
+        rotation: Vec3::ZERO,
+        color: None
+    }
+}
+
+fn lerp(easy: f32, hard: f32, difficulty: f32) -> f32 {
+    easy + (hard - easy) * difficulty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_course() {
+        let generator = CourseGenerator::default();
+        for seed in [0, 1, 42, u64::MAX] {
+            let first = ron::to_string(&generator.course(seed).unwrap()).unwrap();
+            let second = ron::to_string(&generator.course(seed).unwrap()).unwrap();
+            assert_eq!(first, second, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn segments_are_honored() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for segments in [1, 4, 12] {
+            let generator = CourseGenerator {
+                segments,
+                ..default()
+            };
+            //a start and a finish around the segments
+            assert_eq!(generator.build_course(&mut rng).platforms.len(), segments + 2);
+            assert_eq!(generator.build_chunk(&mut rng, Vec3::ZERO).platforms.len(), segments);
+        }
+    }
+
+    #[test]
+    fn difficulty_narrows_platforms() {
+        let easy = CourseGenerator {
+            difficulty: 0.0,
+            segments: 32
+        };
+        let hard = CourseGenerator {
+            difficulty: 1.0,
+            segments: 32
+        };
+        let mut rng = StdRng::seed_from_u64(5);
+        for platform in easy.build_chunk(&mut rng, Vec3::ZERO).platforms {
+            assert!(platform.size.x >= 6.0 * 0.8 - 0.001, "{}", platform.size.x);
+        }
+        for platform in hard.build_chunk(&mut rng, Vec3::ZERO).platforms {
+            assert!(platform.size.x <= 3.0 * 1.2 + 0.001, "{}", platform.size.x);
+        }
+    }
+
+    #[test]
+    fn unreachable_goal_is_rejected() {
+        //the goal is on a platform far beyond the reach of the ball
+        let start = PlatformDef::new(Vec3::new(6.0, PLATFORM_THICKNESS, 6.0), Vec3::new(0.0, -PLATFORM_THICKNESS / 2.0, -3.0));
+        let mut finish = PlatformDef::new(Vec3::new(6.0, PLATFORM_THICKNESS, 6.0), Vec3::new(0.0, -PLATFORM_THICKNESS / 2.0, -30.0));
+        finish.gates.push(GateDef {
+            goal: true,
+            ..GateDef::new(Vec3::new(0.0, PLATFORM_THICKNESS / 2.0, -1.5), 2.0, 1.4)
+        });
+        let level = Level {
+            ball_start: Vec3::new(0.0, 1.0, -2.0),
+            platforms: vec![start, finish],
+            ..default()
+        };
+        assert!(!navigation::solvable(&level));
+
+        let chunk = Chunk {
+            platforms: vec![PlatformDef::new(Vec3::new(4.0, PLATFORM_THICKNESS, 4.0), Vec3::new(0.0, -PLATFORM_THICKNESS / 2.0, -20.0))],
+            cheese: Vec::new(),
+            end: Vec3::new(0.0, 0.0, -22.0)
+        };
+        assert!(!chunk_solvable(&chunk, Vec3::ZERO));
+    }
+}
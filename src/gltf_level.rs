@@ -0,0 +1,133 @@
+//! Level geometry from glTF scenes, set as `scene` in a level file.
+//!
+//! Nodes are recognized by how their name starts, so they can be named in Blender:
+//! - `col_box` its meshes are walls with a box collider around each
+//! - `col_trimesh` its meshes are walls colliding exactly, for slopes and curves
+//! - `sensor` its meshes become invisible sensors, nothing reacts to them beyond their collision events
+//! - `goal` its meshes become an invisible goal
+//! - `cheese` a cheese is placed there instead of the node
+//! - `ball_start` the ball starts there instead of at the level's `ball_start`
+//!
+//! Everything else is only drawn. `levels/sample.level.ron` uses each of them.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
+use bevy_rapier3d::prelude::*;
+
+use crate::level::{CurrentLevel, Goal, LevelEntity, LevelScene, PendingScene, Wall};
+use crate::mesh_collider::MeshCollider;
+use crate::prefab;
+
+/// Turns the named nodes of level scenes into colliders, goals, cheese and the ball's start.
+pub struct GltfLevelPlugin;
+
+impl Plugin for GltfLevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(setup_level_scene);
+    }
+}
+
+/// The nodes of a spawned scene, by name.
+#[derive(SystemParam)]
+struct SceneNodes<'w, 's> {
+    query_children:Query<'w, 's, &'static Children>,
+    query_names:Query<'w, 's, &'static Name>,
+    query_transforms:Query<'w, 's, &'static Transform>,
+    query_parents:Query<'w, 's, &'static Parent>,
+    query_meshes:Query<'w, 's, (), With<Mesh3d>>
+}
+impl SceneNodes<'_, '_> {
+    fn children(&self, entity: Entity) -> Vec<Entity> {
+        self.query_children.get(entity)
+            .map(|children| children.to_vec())
+            .unwrap_or_default()
+    }
+
+    fn name(&self, entity: Entity) -> &str {
+        self.query_names.get(entity).map(|name| name.as_str()).unwrap_or("")
+    }
+
+    /// `node` and its descendants that have a mesh
+    fn meshes(&self, node: Entity) -> Vec<Entity> {
+        std::iter::once(node)
+            .chain(self.query_children.iter_descendants(node))
+            .filter(|entity| self.query_meshes.contains(*entity))
+            .collect()
+    }
+
+    /// The transform of `entity` in the world, before the scene's global transforms are propagated.
+    fn transform(&self, entity: Entity) -> Transform {
+        let mut transform = self.query_transforms.get(entity).copied().unwrap_or_default();
+        for ancestor in self.query_parents.iter_ancestors(entity) {
+            if let Ok(ancestor_transform) = self.query_transforms.get(ancestor) {
+                transform = ancestor_transform.mul_transform(transform);
+            }
+        }
+        transform
+    }
+}
+
+fn setup_level_scene(
+    trigger: Trigger<SceneInstanceReady>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut current_level: ResMut<CurrentLevel>,
+    query_scenes: Query<(), With<LevelScene>>,
+    scene_nodes: SceneNodes,
+){
+    let root = trigger.entity();
+    if !query_scenes.contains(root) {
+        return;
+    }
+
+    let mut ball_start = current_level.ball_start;
+    let mut nodes = scene_nodes.children(root);
+    while let Some(node) = nodes.pop() {
+        let name = scene_nodes.name(node);
+        let meshes = scene_nodes.meshes(node);
+
+        if name.starts_with("col_box") || name.starts_with("col_trimesh") {
+            let shape = if name.starts_with("col_box") { MeshCollider::Aabb } else { MeshCollider::TriMesh };
+            for mesh in meshes {
+                commands.entity(mesh)
+                    .insert(shape)
+                    .insert(Wall);
+            }
+        } else if name.starts_with("sensor") {
+            for mesh in meshes {
+                commands.entity(mesh)
+                    .insert(MeshCollider::Aabb)
+                    .insert(Sensor)
+                    .insert(ActiveEvents::COLLISION_EVENTS)
+                    .insert(Visibility::Hidden);
+            }
+        } else if name.starts_with("goal") {
+            for mesh in meshes {
+                commands.entity(mesh)
+                    .insert(MeshCollider::Aabb)
+                    .insert(Sensor)
+                    .insert(ActiveEvents::COLLISION_EVENTS)
+                    .insert(Goal)
+                    .insert(Visibility::Hidden);
+            }
+            current_level.has_goal = true;
+        } else if name.starts_with("cheese") {
+            let position = scene_nodes.transform(node).translation;
+            let cheese = prefab::spawn_cheese(&mut commands, &asset_server, position);
+            commands.entity(cheese).insert(LevelEntity);
+            commands.entity(node).despawn_recursive();
+            current_level.cheese_left += 1;
+        } else if name.starts_with("ball_start") {
+            ball_start = scene_nodes.transform(node).translation;
+            commands.entity(node).insert(Visibility::Hidden);
+        } else {
+            //only look inside nodes without a meaning of their own
+            nodes.extend(scene_nodes.children(node));
+        }
+    }
+
+    current_level.ball_start = ball_start;
+    //the level starts once the colliders of the nodes are built
+    commands.entity(root).remove::<PendingScene>();
+}
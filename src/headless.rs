@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use bevy::hierarchy::HierarchyPlugin;
+use bevy::prelude::*;
+use bevy::scene::ScenePlugin;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::transform::TransformPlugin;
+use serde::Deserialize;
+
+use crate::input::PlayerInput;
+use crate::level::CurrentLevel;
+use crate::replay::{self, Replay, ReplayMode};
+use crate::score::Score;
+use crate::state::GameState;
+use crate::CheeseballPlugin;
+
+const FRAME_TIME:f32 = 1.0 / 60.0;
+/// frames the level may take to load before the run is given up
+const LOAD_FRAMES:u32 = 600;
+
+/// One entry of an input script: what is held for how many frames.
+/// Buttons count as pressed in the first frame of the step only.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ScriptStep {
+    pub frames:u32,
+    #[serde(default)]
+    pub movement:Vec2,
+    #[serde(default)]
+    pub confirm:bool,
+    #[serde(default)]
+    pub pause:bool,
+    #[serde(default)]
+    pub restart:bool,
+}
+
+/// Input source replacing keyboard and gamepad when running without a window.
+#[derive(Resource, Debug, Default, Deserialize)]
+pub struct ScriptedInput {
+    pub steps:Vec<ScriptStep>,
+    #[serde(skip)]
+    step:usize,
+    #[serde(skip)]
+    frame:u32
+}
+impl ScriptedInput {
+    pub fn new(steps: Vec<ScriptStep>) -> Self {
+        Self {
+            steps,
+            step: 0,
+            frame: 0
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.step >= self.steps.len()
+    }
+}
+
+/// Feeds the script into `PlayerInput`, waiting while a level is still loading.
+pub fn play_script(
+    mut script: ResMut<ScriptedInput>,
+    current_level: Res<CurrentLevel>,
+    state: Res<State<GameState>>,
+    mut player_input: ResMut<PlayerInput>,
+){
+    *player_input = PlayerInput::default();
+    if *state.get() == GameState::Playing && !current_level.spawned {
+        return;
+    }
+    let Some(step) = script.steps.get(script.step).cloned() else {
+        return;
+    };
+    let first_frame = script.frame == 0;
+    *player_input = PlayerInput {
+        movement: step.movement.clamp_length_max(1.0),
+        confirm: first_frame && step.confirm,
+        pause: first_frame && step.pause,
+        restart: first_frame && step.restart,
+        ..default()
+    };
+    script.frame += 1;
+    if script.frame >= step.frames {
+        script.step += 1;
+        script.frame = 0;
+    }
+}
+
+/// An app running the gameplay with `MinimalPlugins`, fixed frame times and no renderer,
+/// starting straight in the first level.
+pub fn headless_app(script: ScriptedInput, seed: Option<u64>) -> App {
+    let mut app = minimal_app(seed);
+    app
+        .insert_resource(script)
+        .add_systems(PreUpdate,play_script);
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Like `headless_app`, but the input comes from a replay.
+pub fn replay_app(replay: Replay) -> App {
+    let mut app = minimal_app(Some(replay.seed));
+    replay::play(&mut app, replay);
+    app.finish();
+    app.cleanup();
+    app
+}
+
+fn minimal_app(seed: Option<u64>) -> App {
+    let mut app = App::new();
+    app
+        .add_plugins((MinimalPlugins,
+                      AssetPlugin::default(),
+                      TransformPlugin,
+                      HierarchyPlugin,
+                      StatesPlugin,
+                      ScenePlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(FRAME_TIME)))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .add_plugins(CheeseballPlugin { headless: true, seed, ..default() })
+        .insert_state(GameState::Playing);
+    app
+}
+
+/// Steps the app until the script is done, false when the level didn't load in time.
+pub fn run_script(app: &mut App) -> bool {
+    run_until(app, |world| world.resource::<ScriptedInput>().finished())
+}
+
+/// Steps the app until every tick of the replay has been played or the level ended,
+/// false when the level didn't load in time.
+pub fn run_replay(app: &mut App) -> bool {
+    run_until(app, |world| {
+        world.resource::<ReplayMode>().finished()
+            || *world.resource::<State<GameState>>().get() != GameState::Playing
+    })
+}
+
+fn run_until(app: &mut App, done: impl Fn(&World) -> bool) -> bool {
+    let mut loading = 0;
+    while !done(app.world()) {
+        app.update();
+        if !app.world().resource::<CurrentLevel>().spawned {
+            loading += 1;
+            if loading > LOAD_FRAMES {
+                warn!("level did not load, giving up");
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Entry point for `--headless <script.ron>`, prints the score when the script ends.
+pub fn run(path: &str, seed: Option<u64>) {
+    let script = match std::fs::read_to_string(path) {
+        Ok(text) => match ron::from_str::<ScriptedInput>(&text) {
+            Ok(script) => script,
+            Err(error) => {
+                eprintln!("could not parse {}: {}", path, error);
+                return;
+            }
+        },
+        Err(error) => {
+            eprintln!("could not read {}: {}", path, error);
+            return;
+        }
+    };
+    let mut app = headless_app(script, seed);
+    run_script(&mut app);
+    print_result(&app);
+}
+
+/// Entry point for `--replay <file> --headless`, prints the score when the replay ends.
+pub fn run_replay_file(path: &str) {
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            return;
+        }
+    };
+    let mut app = replay_app(replay);
+    run_replay(&mut app);
+    print_result(&app);
+}
+
+fn print_result(app: &App) {
+    let score = app.world().resource::<Score>();
+    let state = app.world().resource::<State<GameState>>();
+    println!("state: {:?} level: {} cheese: {} lives: {}",
+             state.get(), score.level, score.cheese, score.lives);
+}
@@ -0,0 +1,233 @@
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::time::RunFixedMainLoopSystem;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::{self, Action, KeyBindings, Rebinding};
+use crate::camera::ThirdPersonCamera;
+use crate::state::GameState;
+use crate::tilt;
+use crate::Ball;
+
+const SPEED:f32= 1.0;
+
+/// What the player wants to do this frame, independent of the input device.
+///
+/// The buttons are only set in the frame they are pressed.
+#[derive(Resource, Default)]
+pub struct PlayerInput {
+    /// x to the right and y forward as seen from the camera, length at most 1
+    pub movement:Vec2,
+    pub confirm:bool,
+    pub pause:bool,
+    pub restart:bool,
+    pub quit:bool,
+    pub toggle_mode:bool,
+    pub toggle_debug:bool,
+    pub bindings:bool,
+    /// whether a reloaded level keeps the ball where it is
+    pub keep_ball:bool,
+    pub editor:bool,
+    /// start an endless run from the menu
+    pub endless:bool
+}
+
+/// The part of `PlayerInput` the gameplay reacts to, sampled once per fixed tick.
+/// This is what replays record and play back.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TickInput {
+    pub movement:Vec2,
+    /// forward of the camera on the ground plane when the tick was sampled
+    pub forward:Vec3,
+    pub restart:bool,
+    pub toggle_mode:bool
+}
+
+/// Buttons pressed since the last fixed tick, so presses in frames without a tick are not lost.
+#[derive(Resource, Default)]
+struct LatchedButtons {
+    restart:bool,
+    toggle_mode:bool
+}
+
+/// How the analog stick deflection is turned into movement.
+#[derive(Resource)]
+pub struct StickSettings {
+    /// deflection below this is ignored
+    pub dead_zone:f32,
+    /// 1.0 is linear, larger values give finer control near the center
+    pub exponent:f32
+}
+impl Default for StickSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.15,
+            exponent: 2.0
+        }
+    }
+}
+impl StickSettings {
+    pub fn shape(&self, stick: Vec2) -> Vec2 {
+        let length = stick.length();
+        if length <= self.dead_zone {
+            return Vec2::ZERO;
+        }
+        let scaled = ((length - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0);
+        stick / length * scaled.powf(self.exponent)
+    }
+}
+
+/// Samples `PlayerInput`, whatever fills it, into `TickInput` and pushes the ball with it.
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<PlayerInput>()
+            .init_resource::<TickInput>()
+            .init_resource::<LatchedButtons>()
+            .init_resource::<tilt::ControlMode>()
+            .add_systems(RunFixedMainLoop,latch_buttons
+                .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop)
+                .run_if(in_state(GameState::Playing)))
+            .add_systems(FixedPreUpdate,sample_input
+                .run_if(in_state(GameState::Playing)))
+            .add_systems(FixedUpdate,input_user
+                .run_if(tilt::force_mode)
+                .run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Fills `PlayerInput` from keyboard and gamepads and offers the rebinding screen.
+pub struct InputDevicesPlugin;
+
+impl Plugin for InputDevicesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(KeyBindings::load())
+            .init_resource::<Rebinding>()
+            .init_resource::<StickSettings>()
+            .add_systems(PreUpdate,(read_keyboard,
+                                    read_gamepad).chain().after(InputSystem))
+            .add_systems(OnEnter(GameState::Bindings),bindings::spawn_bindings_screen)
+            .add_systems(Update,(bindings::rebind,
+                                 bindings::update_bindings_screen).chain()
+                .run_if(in_state(GameState::Bindings)))
+            .add_systems(Update,log_gamepad_connections);
+    }
+}
+
+fn latch_buttons(
+    player_input: Res<PlayerInput>,
+    mut latched: ResMut<LatchedButtons>,
+){
+    latched.restart |= player_input.restart;
+    latched.toggle_mode |= player_input.toggle_mode;
+}
+
+pub fn sample_input(
+    player_input: Res<PlayerInput>,
+    mut latched: ResMut<LatchedButtons>,
+    query_camera: Query<&Transform, With<ThirdPersonCamera>>,
+    mut tick_input: ResMut<TickInput>,
+){
+    *tick_input = TickInput {
+        movement: player_input.movement,
+        forward: ground_forward(query_camera.get_single().ok()),
+        restart: latched.restart,
+        toggle_mode: latched.toggle_mode
+    };
+    *latched = LatchedButtons::default();
+}
+
+fn input_user(
+    tick_input: Res<TickInput>,
+    mut query_forces: Query<&mut ExternalForce, With<Ball>>,
+){
+    //released keys leave a zero force behind
+    let direction = relative_to(tick_input.movement, tick_input.forward);
+    for mut ext_force in query_forces.iter_mut() {
+        ext_force.force = direction * SPEED;
+    }
+}
+
+pub fn read_keyboard(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut player_input: ResMut<PlayerInput>,
+){
+    let mut movement = Vec2::ZERO;
+    if key_bindings.pressed(&keyboard_input, Action::MoveLeft) {
+        movement.x -= 1.0;
+    }
+    if key_bindings.pressed(&keyboard_input, Action::MoveRight) {
+        movement.x += 1.0;
+    }
+    if key_bindings.pressed(&keyboard_input, Action::MoveForward) {
+        movement.y += 1.0;
+    }
+    if key_bindings.pressed(&keyboard_input, Action::MoveBack) {
+        movement.y -= 1.0;
+    }
+    *player_input = PlayerInput {
+        movement: movement.normalize_or_zero(),
+        confirm: key_bindings.just_pressed(&keyboard_input, Action::Confirm),
+        pause: key_bindings.just_pressed(&keyboard_input, Action::Pause),
+        restart: key_bindings.just_pressed(&keyboard_input, Action::Restart),
+        quit: key_bindings.just_pressed(&keyboard_input, Action::Quit),
+        toggle_mode: key_bindings.just_pressed(&keyboard_input, Action::ToggleMode),
+        toggle_debug: key_bindings.just_pressed(&keyboard_input, Action::ToggleDebug),
+        bindings: key_bindings.just_pressed(&keyboard_input, Action::Bindings),
+        keep_ball: key_bindings.just_pressed(&keyboard_input, Action::KeepBall),
+        editor: key_bindings.just_pressed(&keyboard_input, Action::Editor),
+        endless: key_bindings.just_pressed(&keyboard_input, Action::Endless)
+    };
+}
+
+pub fn read_gamepad(
+    stick_settings: Res<StickSettings>,
+    query_gamepads: Query<&Gamepad>,
+    mut player_input: ResMut<PlayerInput>,
+){
+    for gamepad in query_gamepads.iter() {
+        let stick = stick_settings.shape(gamepad.left_stick());
+        player_input.movement = (player_input.movement + stick).clamp_length_max(1.0);
+        player_input.confirm |= gamepad.just_pressed(GamepadButton::South);
+        player_input.pause |= gamepad.just_pressed(GamepadButton::Start);
+        player_input.restart |= gamepad.just_pressed(GamepadButton::Select);
+        player_input.quit |= gamepad.just_pressed(GamepadButton::East);
+        player_input.toggle_mode |= gamepad.just_pressed(GamepadButton::North);
+        player_input.endless |= gamepad.just_pressed(GamepadButton::West);
+    }
+}
+
+pub fn log_gamepad_connections(
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+){
+    for event in connection_events.read() {
+        match &event.connection {
+            GamepadConnection::Connected { name, .. } => info!("gamepad connected: {}", name),
+            GamepadConnection::Disconnected => info!("gamepad disconnected"),
+        }
+    }
+}
+
+/// Where the camera looks, flattened onto the ground plane.
+/// Without a camera the world axes are used, forward being -Z.
+pub fn ground_forward(camera_transform: Option<&Transform>) -> Vec3 {
+    match camera_transform {
+        Some(camera_transform) => {
+            let forward = camera_transform.forward();
+            Vec3::new(forward.x, 0.0, forward.z).normalize_or(Vec3::NEG_Z)
+        }
+        None => Vec3::NEG_Z
+    }
+}
+
+/// Turns movement relative to `forward` into a direction on the ground plane.
+pub fn relative_to(movement: Vec2, forward: Vec3) -> Vec3 {
+    let right = forward.cross(Vec3::Y);
+    right * movement.x + forward * movement.y
+}
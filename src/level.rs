@@ -0,0 +1,694 @@
+use std::path::Path;
+
+use bevy::asset::{io::Reader, AssetLoader, LoadContext, LoadState};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::asset_cache::CachedAssets;
+use crate::camera::ThirdPersonCamera;
+use crate::input::{PlayerInput, TickInput};
+use crate::mesh_collider::{MeshCollider, MeshColliderSet, PendingBody, SceneColliders};
+use crate::physics::{InterpolatedVisual, TickTransforms};
+use crate::prefab;
+use crate::score::{self, Score};
+use crate::state::GameState;
+use crate::Ball;
+
+const LEVELS:[&str; 3] = [
+    "levels/level1.level.ron",
+    "levels/level2.level.ron",
+    "levels/level3.level.ron",
+];
+
+/// A level as authored in a `*.level.ron` file.
+///
+/// Positions of walls and doors are relative to the platform they belong to,
+/// everything else is in world space.
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct Level {
+    /// where the ball starts, unless the scene has a `ball_start` node
+    #[serde(default)]
+    pub ball_start:Vec3,
+    /// the ball is lost when it falls below this height
+    #[serde(default = "default_kill_height")]
+    pub kill_height:f32,
+    #[serde(default)]
+    pub camera:CameraDef,
+    #[serde(default)]
+    pub platforms:Vec<PlatformDef>,
+    #[serde(default)]
+    pub cheese:Vec<Vec3>,
+    /// the goal only opens once every cheese is eaten
+    #[serde(default = "default_goal_requires_cheese")]
+    pub goal_requires_cheese:bool,
+    /// a glTF scene with more geometry, see `gltf_level` for the node names it understands
+    #[serde(default)]
+    pub scene:Option<String>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            ball_start: Vec3::ZERO,
+            kill_height: default_kill_height(),
+            camera: CameraDef::default(),
+            platforms: Vec::new(),
+            cheese: Vec::new(),
+            goal_requires_cheese: default_goal_requires_cheese(),
+            scene: None
+        }
+    }
+}
+impl Level {
+    /// Writes the level as RON, in the format the `LevelLoader` reads.
+    pub fn save(&self, file: impl AsRef<Path>) -> Result<(), LevelSaveError> {
+        let text = ron::ser::to_string_pretty(self, PrettyConfig::default())?;
+        std::fs::write(file, text)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraDef {
+    pub offset:Vec3,
+    pub lookat:Vec3,
+    pub follow:f32
+}
+impl Default for CameraDef {
+    fn default() -> Self {
+        let camera = ThirdPersonCamera::default();
+        Self {
+            offset: camera.ideal_offset,
+            lookat: camera.ideal_lookat,
+            follow: camera.follow
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlatformDef {
+    pub size:Vec3,
+    pub position:Vec3,
+    /// euler angles in degrees
+    #[serde(default)]
+    pub rotation:Vec3,
+    #[serde(default = "default_platform_color")]
+    pub color:(f32, f32, f32),
+    #[serde(default)]
+    pub walls:Vec<BlockDef>,
+    #[serde(default)]
+    pub doors:Vec<BlockDef>,
+    #[serde(default)]
+    pub gates:Vec<GateDef>,
+    #[serde(default)]
+    pub goal:Option<GoalDef>,
+}
+impl PlatformDef {
+    /// A bare platform in the default color.
+    pub fn new(size: Vec3, position: Vec3) -> Self {
+        Self {
+            size,
+            position,
+            rotation: Vec3::ZERO,
+            color: default_platform_color(),
+            walls: Vec::new(),
+            doors: Vec::new(),
+            gates: Vec::new(),
+            goal: None
+        }
+    }
+
+    pub fn has_goal(&self) -> bool {
+        self.goal.is_some() || self.gates.iter().any(|gate| gate.goal)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockDef {
+    pub size:Vec3,
+    pub position:Vec3,
+    /// euler angles in degrees
+    #[serde(default)]
+    pub rotation:Vec3,
+    /// falls back to the default wall or door color
+    #[serde(default)]
+    pub color:Option<(f32, f32, f32)>,
+}
+
+/// A door frame standing on `position`, facing along z before rotation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateDef {
+    pub position:Vec3,
+    /// outer width and height
+    pub width:f32,
+    pub height:f32,
+    #[serde(default = "default_gate_thickness")]
+    pub thickness:f32,
+    /// euler angles in degrees
+    #[serde(default)]
+    pub rotation:Vec3,
+    /// falls back to the door color
+    #[serde(default)]
+    pub color:Option<(f32, f32, f32)>,
+    /// put a goal into the opening
+    #[serde(default)]
+    pub goal:bool,
+}
+
+impl GateDef {
+    /// A gate of default thickness and color, without a goal.
+    pub fn new(position: Vec3, width: f32, height: f32) -> Self {
+        Self {
+            position,
+            width,
+            height,
+            thickness: default_gate_thickness(),
+            rotation: Vec3::ZERO,
+            color: None,
+            goal: false
+        }
+    }
+
+    /// The two posts and the lintel, relative to the gate.
+    pub fn blocks(&self) -> [BlockDef; 3] {
+        let (width, height, thickness) = (self.width, self.height, self.thickness);
+        let post = Vec3::new(thickness, height - thickness, thickness);
+        let post_x = (width - thickness) / 2.0;
+        [
+            BlockDef {
+                size: post,
+                position: Vec3::new(-post_x, post.y / 2.0, 0.0),
+                rotation: Vec3::ZERO,
+                color: self.color
+            },
+            BlockDef {
+                size: post,
+                position: Vec3::new(post_x, post.y / 2.0, 0.0),
+                rotation: Vec3::ZERO,
+                color: self.color
+            },
+            BlockDef {
+                size: Vec3::new(width, thickness, thickness),
+                position: Vec3::new(0.0, height - thickness / 2.0, 0.0),
+                rotation: Vec3::ZERO,
+                color: self.color
+            }
+        ]
+    }
+
+    /// The goal filling the opening, relative to the gate, if the gate has one.
+    pub fn opening_goal(&self) -> Option<GoalDef> {
+        if !self.goal {
+            return None;
+        }
+        let opening = Vec3::new(self.width - 2.0 * self.thickness, self.height - self.thickness, self.thickness);
+        Some(GoalDef {
+            size: opening,
+            position: Vec3::new(0.0, opening.y / 2.0, 0.0)
+        })
+    }
+}
+
+/// A sensor, usually placed inside a door frame, that finishes the level.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalDef {
+    pub size:Vec3,
+    pub position:Vec3,
+}
+
+fn default_goal_requires_cheese() -> bool {
+    true
+}
+
+fn default_kill_height() -> f32 {
+    -10.0
+}
+
+fn default_platform_color() -> (f32, f32, f32) {
+    (1.0, 0.8, 0.6)
+}
+
+fn default_gate_thickness() -> f32 {
+    0.4
+}
+
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum LevelLoaderError {
+    #[error("Could not load level: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse level: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum LevelSaveError {
+    #[error("Could not write level: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not serialize level: {0}")]
+    Ron(#[from] ron::Error),
+}
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let level = ron::de::from_bytes::<Level>(&bytes)?;
+        Ok(level)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+/// Loads level files and moves through the level sequence.
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            .init_resource::<LevelSequence>()
+            .add_event::<LevelCompleteEvent>()
+            .add_event::<LevelStartedEvent>()
+            .add_systems(Startup,setup)
+            .add_systems(OnEnter(GameState::MainMenu),despawn_level)
+            .add_systems(OnExit(GameState::LevelComplete),(despawn_level,
+                                                           load_next_level).chain())
+            .init_resource::<HotReload>()
+            .add_systems(Update,(reload_level,
+                                 spawn_level).chain()
+                .before(MeshColliderSet)
+                .run_if(in_state(GameState::Playing)))
+            .add_systems(Update,start_level
+                .after(MeshColliderSet)
+                .run_if(in_state(GameState::Playing)))
+            .add_systems(Update,toggle_keep_ball)
+            .add_systems(FixedUpdate,(restart_level,
+                                      level_complete.after(score::collision).after(score::fall_off))
+                .run_if(in_state(GameState::Playing)));
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    sequence: Res<LevelSequence>,
+) {
+    if let Some(path) = sequence.current() {
+        commands.insert_resource(CurrentLevel::load(&asset_server, path));
+    }
+}
+
+/// The level files in the order they are played.
+#[derive(Resource)]
+pub struct LevelSequence {
+    pub levels:Vec<String>,
+    pub index:usize
+}
+impl Default for LevelSequence {
+    fn default() -> Self {
+        Self {
+            levels: LEVELS.iter().map(|path| path.to_string()).collect(),
+            index: 0
+        }
+    }
+}
+impl LevelSequence {
+    pub fn current(&self) -> Option<&str> {
+        self.levels.get(self.index).map(|path| path.as_str())
+    }
+}
+
+/// The level that is shown and whether it has been spawned and started yet.
+#[derive(Resource)]
+pub struct CurrentLevel {
+    pub path:String,
+    pub handle:Handle<Level>,
+    /// the entities are spawned, the level starts once their colliders are built
+    pub building:bool,
+    /// the level has started and is played
+    pub spawned:bool,
+    pub cheese_left:usize,
+    pub ball_start:Vec3,
+    pub kill_height:f32,
+    pub has_goal:bool,
+    pub goal_requires_cheese:bool,
+    /// cheese score when the level was entered, restored on restart
+    pub cheese_at_start:i32,
+    /// the level is rebuilt around the ball that is still there
+    pub keep_ball:bool,
+    /// the attempt is over, nothing counts until the state changes
+    pub ended:bool
+}
+impl CurrentLevel {
+    pub fn load(asset_server: &AssetServer, path: &str) -> Self {
+        Self::new(path, asset_server.load(path.to_string()))
+    }
+
+    /// A level that was not loaded from a file, `path` only names it.
+    pub fn new(path: &str, handle: Handle<Level>) -> Self {
+        Self {
+            path: path.to_string(),
+            handle,
+            building: false,
+            spawned: false,
+            cheese_left: 0,
+            ball_start: Vec3::ZERO,
+            kill_height: default_kill_height(),
+            has_goal: false,
+            goal_requires_cheese: default_goal_requires_cheese(),
+            cheese_at_start: 0,
+            keep_ball: false,
+            ended: false
+        }
+    }
+}
+
+/// Everything that belongs to the current level and is removed when it ends.
+#[derive(Component)]
+pub struct LevelEntity;
+
+#[derive(Component)]
+pub struct Goal;
+
+/// The part of the `Level` an entity was spawned from, so picked entities can be traced back to the file.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelElement {
+    Platform(usize),
+    /// platform and index into its walls
+    Wall(usize, usize),
+    Door(usize, usize),
+    Gate(usize, usize),
+    /// the goal of a platform
+    Goal(usize),
+    Cheese(usize),
+    BallStart
+}
+
+/// Walls and doors, the ball makes sparks when it hits them.
+#[derive(Component)]
+pub struct Wall;
+
+#[derive(Event)]
+pub struct LevelCompleteEvent;
+
+/// Sent when a level has started, with its colliders and the ball in place, also after a restart.
+#[derive(Event)]
+pub struct LevelStartedEvent {
+    pub path:String
+}
+
+pub fn spawn_level(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    levels: Res<Assets<Level>>,
+    score: Res<Score>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut cached_assets: CachedAssets,
+    mut query_camera: Query<&mut ThirdPersonCamera>,
+){
+    if current_level.spawned || current_level.building {
+        return;
+    }
+    let Some(level) = levels.get(&current_level.handle) else {
+        return;
+    };
+    current_level.building = true;
+    current_level.cheese_left = level.cheese.len();
+    current_level.ball_start = level.ball_start;
+    current_level.kill_height = level.kill_height;
+    current_level.has_goal = level.platforms.iter().any(|platform| platform.has_goal());
+    current_level.goal_requires_cheese = level.goal_requires_cheese;
+    current_level.cheese_at_start = score.cheese;
+
+    //camera
+    for mut camera in query_camera.iter_mut() {
+        camera.ideal_offset = level.camera.offset;
+        camera.ideal_lookat = level.camera.lookat;
+        camera.follow = level.camera.follow;
+        camera.lookat_aviabel = false;
+    }
+
+    spawn_level_entities(&mut commands, &asset_server, &mut cached_assets, level);
+}
+
+/// Starts the spawned level once its scene is set up and every collider in it is built,
+/// so the ball never has a tick without the floor below it.
+pub fn start_level(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut cached_assets: CachedAssets,
+    query_scenes: Query<(Entity, &SceneRoot), With<PendingScene>>,
+    query_pending: Query<(), Or<(With<MeshCollider>, With<PendingBody>, With<SceneColliders>)>>,
+    mut event_level_started: EventWriter<LevelStartedEvent>,
+){
+    if !current_level.building {
+        return;
+    }
+    let mut waiting = !query_pending.is_empty();
+    for (entity, scene) in query_scenes.iter() {
+        if let Some(LoadState::Failed(error)) = asset_server.get_load_state(&scene.0) {
+            //played without the scene
+            warn!("{}", error);
+            commands.entity(entity).remove::<PendingScene>();
+        } else {
+            waiting = true;
+        }
+    }
+    if waiting {
+        return;
+    }
+    current_level.building = false;
+    current_level.spawned = true;
+    current_level.ended = false;
+
+    //a scene may have moved the start
+    if !current_level.keep_ball {
+        spawn_ball(&mut commands, &mut cached_assets, current_level.ball_start);
+    }
+    current_level.keep_ball = false;
+
+    event_level_started.send(LevelStartedEvent {
+        path: current_level.path.clone()
+    });
+}
+
+/// Whether a level changed on disk is rebuilt around the ball or from scratch.
+/// Files are only watched with the `hot_reload` feature.
+#[derive(Resource)]
+pub struct HotReload {
+    pub keep_ball:bool
+}
+impl Default for HotReload {
+    fn default() -> Self {
+        Self {
+            keep_ball: true
+        }
+    }
+}
+
+pub fn toggle_keep_ball(
+    player_input: Res<PlayerInput>,
+    mut hot_reload: ResMut<HotReload>,
+){
+    if player_input.keep_ball {
+        hot_reload.keep_ball = !hot_reload.keep_ball;
+        info!("keep ball on reload: {}", hot_reload.keep_ball);
+    }
+}
+
+/// Rebuilds the level when its file or scene changed, like a restart.
+/// Changes of a level's file and scene.
+#[derive(SystemParam)]
+pub struct LevelChanges<'w, 's> {
+    level_events:EventReader<'w, 's, AssetEvent<Level>>,
+    scene_events:EventReader<'w, 's, AssetEvent<Scene>>,
+    query_scene:Query<'w, 's, &'static SceneRoot, With<LevelScene>>
+}
+impl LevelChanges<'_, '_> {
+    /// Whether the level or its scene changed since the last call.
+    pub fn changed(&mut self, level: &Handle<Level>) -> bool {
+        //every event is read, so none is left over to rebuild again next frame
+        let level_changed = self.level_events.read()
+            .filter(|event| event.is_modified(level))
+            .count() > 0;
+        let query_scene = &self.query_scene;
+        let scene_changed = self.scene_events.read()
+            .filter(|event| query_scene.iter().any(|scene| event.is_modified(&scene.0)))
+            .count() > 0;
+        level_changed || scene_changed
+    }
+}
+
+pub fn reload_level(
+    mut commands: Commands,
+    mut level_changes: LevelChanges,
+    hot_reload: Res<HotReload>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut score: ResMut<Score>,
+    query_level: Query<(Entity, Has<Ball>, Has<InterpolatedVisual>), With<LevelEntity>>,
+    mut query_tick_transforms: Query<(&Transform, &mut TickTransforms), With<Ball>>,
+){
+    if !level_changes.changed(&current_level.handle) || !current_level.spawned {
+        return;
+    }
+    info!("{} changed, rebuilding", current_level.path);
+    for (entity, ball, visual) in query_level.iter() {
+        if hot_reload.keep_ball && (ball || visual) {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+    //the kept ball stays put while the level is rebuilt around it
+    for (transform, mut tick_transforms) in query_tick_transforms.iter_mut() {
+        tick_transforms.teleport(*transform);
+    }
+    score.cheese = current_level.cheese_at_start;
+    current_level.spawned = false;
+    current_level.keep_ball = hot_reload.keep_ball;
+}
+
+/// The scene of a level loaded from glTF.
+#[derive(Component)]
+pub struct LevelScene;
+
+/// A level scene whose named nodes are not set up yet, see `gltf_level`.
+#[derive(Component)]
+pub struct PendingScene;
+
+/// Spawns the platforms, cheese and scene of `level`, each tagged with the `LevelElement` it comes from.
+pub fn spawn_level_entities(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    cached_assets: &mut CachedAssets,
+    level: &Level
+){
+    //platforms
+    for (index, platform) in level.platforms.iter().enumerate() {
+        let entities = prefab::spawn_platform(commands, cached_assets, platform);
+        commands.entity(entities.platform)
+            .insert(LevelElement::Platform(index))
+            .insert(LevelEntity);
+        for (wall_index, wall) in entities.walls.iter().enumerate() {
+            commands.entity(*wall).insert(LevelElement::Wall(index, wall_index));
+        }
+        for (door_index, door) in entities.doors.iter().enumerate() {
+            commands.entity(*door).insert(LevelElement::Door(index, door_index));
+        }
+        for (gate_index, gate) in entities.gates.iter().enumerate() {
+            commands.entity(*gate).insert(LevelElement::Gate(index, gate_index));
+        }
+        if let Some(goal) = entities.goal {
+            commands.entity(goal).insert(LevelElement::Goal(index));
+        }
+    }
+
+    //cheese
+    for (index, cheese_position) in level.cheese.iter().enumerate() {
+        let cheese = prefab::spawn_cheese(commands, asset_server, *cheese_position);
+        commands.entity(cheese)
+            .insert(LevelElement::Cheese(index))
+            .insert(LevelEntity);
+    }
+
+    if let Some(scene) = &level.scene {
+        commands.spawn(SceneRoot(asset_server.load(scene.clone())))
+            .insert(RigidBody::Fixed)
+            .insert(LevelScene)
+            .insert(PendingScene)
+            .insert(LevelEntity);
+    }
+}
+
+pub fn spawn_ball(
+    commands: &mut Commands,
+    cached_assets: &mut CachedAssets,
+    position: Vec3
+){
+    let (ball, visual) = prefab::spawn_ball(commands, cached_assets, position);
+    commands.entity(ball).insert(LevelEntity);
+    commands.entity(visual).insert(LevelEntity);
+}
+
+pub fn level_complete(
+    mut event_level_complete: EventReader<LevelCompleteEvent>,
+    sequence: Res<LevelSequence>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if event_level_complete.is_empty() {
+        return;
+    }
+    event_level_complete.clear();
+    if current_level.ended {
+        return;
+    }
+    current_level.ended = true;
+
+    if sequence.index + 1 < sequence.levels.len() {
+        next_state.set(GameState::LevelComplete);
+    } else {
+        next_state.set(GameState::Finished);
+    }
+}
+
+pub fn load_next_level(
+    asset_server: Res<AssetServer>,
+    mut sequence: ResMut<LevelSequence>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut score: ResMut<Score>,
+){
+    sequence.index += 1;
+    if let Some(path) = sequence.current() {
+        score.level += 1;
+        *current_level = CurrentLevel::load(&asset_server, path);
+    }
+}
+
+pub fn restart_level(
+    mut commands: Commands,
+    tick_input: Res<TickInput>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut score: ResMut<Score>,
+    query_level: Query<Entity, With<LevelEntity>>,
+){
+    if !tick_input.restart || !current_level.spawned {
+        return;
+    }
+    for entity in query_level.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    score.cheese = current_level.cheese_at_start;
+    current_level.spawned = false;
+}
+
+pub fn despawn_level(
+    mut commands: Commands,
+    query_level: Query<Entity, With<LevelEntity>>,
+){
+    for entity in query_level.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,88 @@
+//! The ball, camera and level mechanics of bevy_cheeseball, bundled as `CheeseballPlugin`.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+pub mod asset_cache;
+pub mod bindings;
+pub mod camera;
+pub mod editor;
+pub mod effects;
+pub mod endless;
+pub mod generator;
+pub mod gltf_level;
+pub mod headless;
+pub mod input;
+pub mod level;
+pub mod lifetime;
+pub mod mesh_collider;
+pub mod navigation;
+pub mod particles;
+pub mod physics;
+pub mod prefab;
+pub mod replay;
+pub mod rng;
+pub mod score;
+pub mod state;
+pub mod tilt;
+
+use input::PlayerInput;
+
+#[derive(Component)]
+pub struct Ball;
+
+#[derive(Component)]
+pub struct Cheese;
+
+/// The whole game. With `headless` set only the simulation is added,
+/// leaving out input devices, the scoreboard, screens and debug rendering.
+#[derive(Default)]
+pub struct CheeseballPlugin {
+    pub headless:bool,
+    pub physics:physics::PhysicsSettings,
+    /// seed of `GameRng`, random when not set
+    pub seed:Option<u64>
+}
+
+impl Plugin for CheeseballPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        info!("seed: {}", seed);
+        app
+            .insert_resource(rng::GameRng::new(seed))
+            .add_plugins(physics::PhysicsPlugin { settings: self.physics });
+        app.add_plugins((asset_cache::AssetCachePlugin,
+                         mesh_collider::MeshColliderPlugin,
+                         state::GameStatePlugin,
+                         level::LevelPlugin,
+                         gltf_level::GltfLevelPlugin,
+                         camera::CameraPlugin,
+                         input::InputPlugin,
+                         tilt::TiltPlugin,
+                         score::ScorePlugin,
+                         lifetime::LifetimePlugin,
+                         effects::EffectsPlugin,
+                         replay::ReplayPlugin,
+                         endless::EndlessPlugin));
+        if self.headless {
+            return;
+        }
+        app
+            .add_plugins((input::InputDevicesPlugin,
+                          score::ScoreboardPlugin,
+                          state::ScreensPlugin,
+                          asset_cache::AssetCountPlugin,
+                          editor::EditorPlugin,
+                          RapierDebugRenderPlugin::default()))
+            .add_systems(Update,toggle_debug_render);
+    }
+}
+
+fn toggle_debug_render(
+    player_input: Res<PlayerInput>,
+    mut debug_render: ResMut<DebugRenderContext>,
+){
+    if player_input.toggle_debug {
+        debug_render.enabled = !debug_render.enabled;
+    }
+}
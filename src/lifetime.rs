@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+
+/// How long an entity lasts and what happens when the time is up.
+/// An expired entity is despawned, or only loses its `Lifetime` when `despawn` is off.
+#[derive(Component, Debug, Clone)]
+pub struct Lifetime {
+    pub duration:f32,
+    pub elapsed:f32,
+    pub despawn:bool,
+    /// send `LifetimeExpiredEvent` when the time is up
+    pub notify:bool
+}
+impl Lifetime {
+    pub fn despawn_after(duration: f32) -> Self {
+        Self {
+            duration,
+            elapsed: 0.0,
+            despawn: true,
+            notify: false
+        }
+    }
+
+    /// Keeps the entity when the time is up, for things that are reused.
+    pub fn expire_after(duration: f32) -> Self {
+        Self {
+            despawn: false,
+            ..Self::despawn_after(duration)
+        }
+    }
+
+    pub fn with_event(self) -> Self {
+        Self {
+            notify: true,
+            ..self
+        }
+    }
+
+    /// how much of the lifetime has passed, 0 to 1
+    pub fn fraction(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        (self.elapsed / self.duration).min(1.0)
+    }
+
+    pub fn expired(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// Fades the alpha of the entity's material to zero, starting at `from` of the lifetime.
+/// The entity gets its own copy of the material when this is added, others sharing it keep theirs.
+#[derive(Component, Debug, Clone)]
+pub struct FadeOut {
+    pub from:f32
+}
+
+/// Shrinks the entity from `scale` to nothing, starting at `from` of the lifetime.
+#[derive(Component, Debug, Clone)]
+pub struct ShrinkOut {
+    pub from:f32,
+    pub scale:Vec3
+}
+
+#[derive(Event)]
+pub struct LifetimeExpiredEvent(pub Entity);
+
+/// Counts down `Lifetime`s and applies `FadeOut` and `ShrinkOut`.
+pub struct LifetimePlugin;
+
+impl Plugin for LifetimePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<LifetimeExpiredEvent>()
+            .add_systems(Update,(tick_lifetimes,
+                                 own_material,
+                                 (fade_out,
+                                  shrink_out)).chain());
+    }
+}
+
+pub fn tick_lifetimes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Lifetime)>,
+    mut event_expired: EventWriter<LifetimeExpiredEvent>,
+){
+    for (entity, mut lifetime) in query.iter_mut() {
+        lifetime.elapsed += time.delta_secs();
+        if !lifetime.expired() {
+            continue;
+        }
+        if lifetime.notify {
+            event_expired.send(LifetimeExpiredEvent(entity));
+        }
+        if lifetime.despawn {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            commands.entity(entity).remove::<Lifetime>();
+        }
+    }
+}
+
+/// 1 before `from`, going down to 0 at the end of the lifetime
+fn remaining(lifetime: &Lifetime, from: f32) -> f32 {
+    if from >= 1.0 {
+        return 1.0;
+    }
+    1.0 - ((lifetime.fraction() - from) / (1.0 - from)).clamp(0.0, 1.0)
+}
+
+/// Gives fading entities a material of their own, cached materials are shared by many.
+fn own_material(
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut query: Query<&mut MeshMaterial3d<StandardMaterial>, Added<FadeOut>>,
+){
+    for mut material in query.iter_mut() {
+        let Some(copy) = materials.get(&material.0).cloned() else {
+            continue;
+        };
+        material.0 = materials.add(copy);
+    }
+}
+
+fn fade_out(
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<(&Lifetime, &FadeOut, &MeshMaterial3d<StandardMaterial>)>,
+){
+    for (lifetime, fade_out, material) in query.iter() {
+        let Some(material) = materials.get_mut(&material.0) else {
+            continue;
+        };
+        material.base_color.set_alpha(remaining(lifetime, fade_out.from));
+        if material.alpha_mode == AlphaMode::Opaque {
+            material.alpha_mode = AlphaMode::Blend;
+        }
+    }
+}
+
+fn shrink_out(
+    mut query: Query<(&Lifetime, &ShrinkOut, &mut Transform)>,
+){
+    for (lifetime, shrink_out, mut transform) in query.iter_mut() {
+        transform.scale = shrink_out.scale * remaining(lifetime, shrink_out.from);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn fraction_goes_from_zero_to_one() {
+        let mut lifetime = Lifetime::despawn_after(2.0);
+        assert_eq!(lifetime.fraction(), 0.0);
+        lifetime.elapsed = 0.5;
+        assert_eq!(lifetime.fraction(), 0.25);
+        lifetime.elapsed = 3.0;
+        assert_eq!(lifetime.fraction(), 1.0);
+        assert_eq!(Lifetime::despawn_after(0.0).fraction(), 1.0);
+    }
+
+    #[test]
+    fn remaining_starts_going_down_at_from() {
+        let mut lifetime = Lifetime::despawn_after(1.0);
+        lifetime.elapsed = 0.25;
+        assert_eq!(remaining(&lifetime, 0.5), 1.0);
+        lifetime.elapsed = 0.75;
+        assert_eq!(remaining(&lifetime, 0.5), 0.5);
+        lifetime.elapsed = 1.0;
+        assert_eq!(remaining(&lifetime, 0.5), 0.0);
+        assert_eq!(remaining(&lifetime, 1.0), 1.0);
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app
+            .init_resource::<Time>()
+            .add_event::<LifetimeExpiredEvent>()
+            .add_systems(Update,tick_lifetimes);
+        app
+    }
+
+    /// Runs a frame `seconds` long and returns the entities that expired with an event.
+    fn step(app: &mut App, seconds: f32) -> Vec<Entity> {
+        app.world_mut().resource_mut::<Time>().advance_by(Duration::from_secs_f32(seconds));
+        app.update();
+        app.world().resource::<Events<LifetimeExpiredEvent>>()
+            .iter_current_update_events()
+            .map(|event| event.0)
+            .collect()
+    }
+
+    #[test]
+    fn expired_entity_is_despawned() {
+        let mut app = app();
+        let entity = app.world_mut().spawn(Lifetime::despawn_after(1.0)).id();
+        assert!(step(&mut app, 0.5).is_empty());
+        assert!(app.world().entities().contains(entity));
+        assert!(step(&mut app, 0.6).is_empty());
+        assert!(!app.world().entities().contains(entity));
+    }
+
+    #[test]
+    fn expired_entity_is_kept() {
+        let mut app = app();
+        let entity = app.world_mut().spawn(Lifetime::expire_after(1.0)).id();
+        step(&mut app, 1.1);
+        assert!(app.world().entities().contains(entity));
+        assert!(!app.world().entity(entity).contains::<Lifetime>());
+    }
+
+    #[test]
+    fn expiry_is_sent_when_asked_for() {
+        let mut app = app();
+        let despawned = app.world_mut().spawn(Lifetime::despawn_after(1.0).with_event()).id();
+        let kept = app.world_mut().spawn(Lifetime::expire_after(1.0).with_event()).id();
+        app.world_mut().spawn(Lifetime::despawn_after(1.0));
+        assert!(step(&mut app, 0.5).is_empty());
+        let mut expired = step(&mut app, 0.6);
+        expired.sort();
+        let mut expected = vec![despawned, kept];
+        expected.sort();
+        assert_eq!(expired, expected);
+    }
+}
@@ -0,0 +1,123 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+/// Gives the entity a collider matching its `Mesh3d` once the mesh is loaded.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshCollider {
+    /// a cuboid around the mesh, cheap and exact for boxes
+    Aabb,
+    ConvexHull,
+    TriMesh
+}
+
+/// Puts a `MeshCollider` on every mesh of a `SceneRoot` once the scene has spawned.
+/// `fallback` is used on the root itself when the scene can't be loaded.
+#[derive(Component, Debug, Clone)]
+pub struct SceneColliders {
+    pub shape:MeshCollider,
+    pub fallback:Collider
+}
+
+/// A body that is only added once its colliders exist, so it has mass and doesn't fall through the floor.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PendingBody(pub RigidBody);
+
+/// Builds colliders from render meshes, for geometry that only a glTF scene knows the shape of.
+/// Levels don't start while any of these are still waiting.
+pub struct MeshColliderPlugin;
+
+impl Plugin for MeshColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update,(scene_colliders,
+                                mesh_colliders,
+                                activate_bodies).chain()
+            .in_set(MeshColliderSet));
+    }
+}
+
+/// The systems building colliders, levels are spawned before and started after them.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MeshColliderSet;
+
+fn collider_from_mesh(mesh: &Mesh, shape: MeshCollider) -> Option<Collider> {
+    match shape {
+        MeshCollider::Aabb => {
+            let aabb = mesh.compute_aabb()?;
+            let half = Vec3::from(aabb.half_extents);
+            let center = Vec3::from(aabb.center);
+            let cuboid = Collider::cuboid(half.x, half.y, half.z);
+            if center.length_squared() < f32::EPSILON {
+                Some(cuboid)
+            } else {
+                Some(Collider::compound(vec![(center, Quat::IDENTITY, cuboid)]))
+            }
+        }
+        MeshCollider::ConvexHull => Collider::from_bevy_mesh(mesh, &ComputedColliderShape::ConvexHull),
+        MeshCollider::TriMesh => Collider::from_bevy_mesh(mesh, &ComputedColliderShape::TriMesh(TriMeshFlags::default()))
+    }
+}
+
+fn mesh_colliders(
+    mut commands: Commands,
+    meshes: Res<Assets<Mesh>>,
+    query: Query<(Entity, &Mesh3d, &MeshCollider), Without<Collider>>,
+){
+    for (entity, mesh, shape) in query.iter() {
+        let Some(mesh) = meshes.get(&mesh.0) else {
+            continue;
+        };
+        match collider_from_mesh(mesh, *shape) {
+            Some(collider) => {
+                commands.entity(entity).insert(collider);
+            }
+            None => warn!("could not build a {:?} collider for {}", shape, entity)
+        }
+        commands.entity(entity).remove::<MeshCollider>();
+    }
+}
+
+fn scene_colliders(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    query_roots: Query<(Entity, &SceneRoot, &SceneColliders)>,
+    query_children: Query<&Children>,
+    query_meshes: Query<(), With<Mesh3d>>,
+){
+    for (root, scene, scene_colliders) in query_roots.iter() {
+        if let Some(LoadState::Failed(_)) = asset_server.get_load_state(&scene.0) {
+            commands.entity(root)
+                .insert(scene_colliders.fallback.clone())
+                .remove::<SceneColliders>();
+            continue;
+        }
+        let meshes:Vec<Entity> = query_children.iter_descendants(root)
+            .filter(|entity| query_meshes.contains(*entity))
+            .collect();
+        if meshes.is_empty() {
+            //not spawned yet
+            continue;
+        }
+        for entity in meshes {
+            commands.entity(entity).insert(scene_colliders.shape);
+        }
+        commands.entity(root).remove::<SceneColliders>();
+    }
+}
+
+fn activate_bodies(
+    mut commands: Commands,
+    query_pending: Query<(Entity, &PendingBody, Has<Collider>)>,
+    query_children: Query<&Children>,
+    query_colliders: Query<(), With<Collider>>,
+){
+    for (entity, pending, has_collider) in query_pending.iter() {
+        let ready = has_collider || query_children.iter_descendants(entity)
+            .any(|child| query_colliders.contains(child));
+        if ready {
+            commands.entity(entity)
+                .insert(pending.0)
+                .remove::<PendingBody>();
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Where the ball can get to in a level, judged from a graph over the platform tops.
+//!
+//! The tops are cut into cells the ball can stand on without touching a wall. Cells next to
+//! each other are linked, and cells of different platforms are linked when the ball can roll
+//! or fall from one to the other. The ball can't climb, so a link never goes more than a step up.
+//! Geometry from a glTF scene is not known here.
+
+use std::ops::Range;
+
+use bevy::prelude::*;
+
+use crate::level::{BlockDef, GoalDef, Level, PlatformDef};
+use crate::prefab;
+
+/// side of a cell
+const CELL:f32 = 0.25;
+pub const BALL_RADIUS:f32 = 0.5;
+/// how much higher a platform may be and still be rolled onto
+const STEP_HEIGHT:f32 = 0.1;
+/// speed the ball is assumed to leave an edge with, kept low to be on the safe side
+const LAUNCH_SPEED:f32 = 3.0;
+const GRAVITY:f32 = 9.81;
+
+/// The widest gap the ball gets across when the far side is `drop` lower.
+/// Gaps narrower than the ball are rolled over, wider ones need the ball to fly while falling.
+pub fn gap_reach(drop: f32) -> f32 {
+    BALL_RADIUS + LAUNCH_SPEED * (2.0 * drop.max(0.0) / GRAVITY).sqrt()
+}
+
+/// Cells on the platform tops and the cells the ball can get to from each.
+pub struct NavGraph {
+    /// top center of each cell in world space
+    cells:Vec<Vec3>,
+    links:Vec<Vec<usize>>
+}
+
+impl NavGraph {
+    pub fn new(level: &Level) -> Self {
+        let mut graph = NavGraph {
+            cells: Vec::new(),
+            links: Vec::new()
+        };
+        let platforms:Vec<Range<usize>> = level.platforms.iter()
+            .map(|platform| graph.add_platform(platform))
+            .collect();
+        for (a, cells_a) in platforms.iter().enumerate() {
+            for (b, cells_b) in platforms.iter().enumerate() {
+                if a != b {
+                    graph.link_platforms(cells_a.clone(), cells_b.clone());
+                }
+            }
+        }
+        graph
+    }
+
+    /// Adds the free cells of a platform, linked to their neighbors, and returns their indices.
+    fn add_platform(&mut self, platform: &PlatformDef) -> Range<usize> {
+        let transform = Transform {
+            translation: platform.position,
+            rotation: prefab::rotation(platform.rotation),
+            ..default()
+        };
+        let half = platform.size / 2.0;
+        let columns = ((platform.size.x / CELL) as usize).max(1);
+        let rows = ((platform.size.z / CELL) as usize).max(1);
+        let first = self.cells.len();
+        let mut grid:Vec<Option<usize>> = vec![None; columns * rows];
+        for row in 0..rows {
+            for column in 0..columns {
+                let local = Vec3::new(-half.x + (column as f32 + 0.5) * platform.size.x / columns as f32,
+                                      half.y,
+                                      -half.z + (row as f32 + 0.5) * platform.size.z / rows as f32);
+                if blocked(platform, local) {
+                    continue;
+                }
+                grid[row * columns + column] = Some(self.cells.len());
+                self.cells.push(transform.transform_point(local));
+                self.links.push(Vec::new());
+            }
+        }
+
+        //rolling around on the platform goes both ways
+        for row in 0..rows {
+            for column in 0..columns {
+                let Some(cell) = grid[row * columns + column] else {
+                    continue;
+                };
+                let right = (column + 1 < columns).then(|| grid[row * columns + column + 1]).flatten();
+                let below = (row + 1 < rows).then(|| grid[(row + 1) * columns + column]).flatten();
+                for neighbor in right.into_iter().chain(below) {
+                    self.links[cell].push(neighbor);
+                    self.links[neighbor].push(cell);
+                }
+            }
+        }
+        first..self.cells.len()
+    }
+
+    /// Links cells of `from` to the cells of `to` the ball can roll or fall onto.
+    fn link_platforms(&mut self, from: Range<usize>, to: Range<usize>) {
+        let (Some((from_min, from_max)), Some((to_min, to_max))) = (self.bounds(from.clone()), self.bounds(to.clone())) else {
+            return;
+        };
+        let highest_drop = from_max.y - to_min.y;
+        if highest_drop < -STEP_HEIGHT {
+            return;
+        }
+        let reach = CELL + gap_reach(highest_drop);
+        if horizontal_gap(from_min, from_max, to_min, to_max) > reach {
+            return;
+        }
+
+        //only cells near the other platform can link
+        let near = |cell: Vec3, min: Vec3, max: Vec3| horizontal_gap(cell, cell, min, max) <= reach;
+        let edge_from:Vec<usize> = from.filter(|cell| near(self.cells[*cell], to_min, to_max)).collect();
+        let edge_to:Vec<usize> = to.filter(|cell| near(self.cells[*cell], from_min, from_max)).collect();
+        for &a in edge_from.iter() {
+            for &b in edge_to.iter() {
+                let (cell_a, cell_b) = (self.cells[a], self.cells[b]);
+                let drop = cell_a.y - cell_b.y;
+                let distance = Vec2::new(cell_a.x - cell_b.x, cell_a.z - cell_b.z).length();
+                if drop >= -STEP_HEIGHT && distance <= CELL + gap_reach(drop) {
+                    self.links[a].push(b);
+                }
+            }
+        }
+    }
+
+    fn bounds(&self, cells: Range<usize>) -> Option<(Vec3, Vec3)> {
+        let cells = &self.cells[cells];
+        let first = *cells.first()?;
+        Some(cells.iter().fold((first, first), |(min, max), cell| (min.min(*cell), max.max(*cell))))
+    }
+
+    /// The cells reached from where the ball lands when dropped at `start`.
+    pub fn reachable(&self, start: Vec3) -> Reachable {
+        //the ball lands on the highest cell below it
+        let landing = self.cells.iter().enumerate()
+            .filter(|(_, cell)| cell.y <= start.y + STEP_HEIGHT
+                && Vec2::new(cell.x - start.x, cell.z - start.z).length() <= CELL)
+            .max_by(|(_, a), (_, b)| a.y.total_cmp(&b.y))
+            .map(|(index, _)| index);
+
+        let mut reached = vec![false; self.cells.len()];
+        let mut open:Vec<usize> = landing.into_iter().collect();
+        for cell in open.iter() {
+            reached[*cell] = true;
+        }
+        while let Some(cell) = open.pop() {
+            for &next in self.links[cell].iter() {
+                if !reached[next] {
+                    reached[next] = true;
+                    open.push(next);
+                }
+            }
+        }
+        Reachable {
+            cells: self.cells.iter().zip(reached)
+                .filter(|(_, reached)| *reached)
+                .map(|(cell, _)| *cell)
+                .collect()
+        }
+    }
+}
+
+/// The cells the ball can get to.
+pub struct Reachable {
+    pub cells:Vec<Vec3>
+}
+
+impl Reachable {
+    /// Whether the ball can touch something at `point`, like a cheese that fell onto a platform.
+    pub fn reaches(&self, point: Vec3) -> bool {
+        self.cells.iter().any(|cell| cell.y <= point.y + STEP_HEIGHT
+            && Vec2::new(cell.x - point.x, cell.z - point.z).length() <= BALL_RADIUS + CELL)
+    }
+
+    /// Whether the ball can roll into a goal box placed by `transform`.
+    pub fn reaches_goal(&self, transform: Transform, goal: &GoalDef) -> bool {
+        let half = goal.size / 2.0;
+        self.cells.iter().any(|cell| {
+            let local = transform.rotation.inverse() * (*cell - transform.translation);
+            local.x.abs() <= half.x + BALL_RADIUS
+                && local.z.abs() <= half.z + BALL_RADIUS
+                && local.y <= half.y
+                && local.y + 2.0 * BALL_RADIUS >= -half.y
+        })
+    }
+}
+
+/// Whether the ball can eat every cheese and, if the level has goals, get into one.
+pub fn solvable(level: &Level) -> bool {
+    let reachable = NavGraph::new(level).reachable(level.ball_start);
+    let goals = goals(level);
+    level.cheese.iter().all(|cheese| reachable.reaches(*cheese))
+        && (goals.is_empty() || goals.iter().any(|(transform, goal)| reachable.reaches_goal(*transform, goal)))
+}
+
+/// The goals of a level, with the transform placing each in the world.
+pub fn goals(level: &Level) -> Vec<(Transform, GoalDef)> {
+    let mut goals = Vec::new();
+    for platform in level.platforms.iter() {
+        let platform_transform = Transform {
+            translation: platform.position,
+            rotation: prefab::rotation(platform.rotation),
+            ..default()
+        };
+        if let Some(goal) = &platform.goal {
+            goals.push((platform_transform * Transform::from_translation(goal.position), goal.clone()));
+        }
+        for gate in platform.gates.iter() {
+            if let Some(goal) = gate.opening_goal() {
+                let gate_transform = Transform {
+                    translation: gate.position,
+                    rotation: prefab::rotation(gate.rotation),
+                    ..default()
+                };
+                goals.push((platform_transform * gate_transform * Transform::from_translation(goal.position), goal));
+            }
+        }
+    }
+    goals
+}
+
+/// Whether the ball standing on `point` of the platform's top would touch a wall, door or gate.
+fn blocked(platform: &PlatformDef, point: Vec3) -> bool {
+    platform.walls.iter()
+        .chain(platform.doors.iter())
+        .any(|block| block_in_way(block, point))
+        || platform.gates.iter().any(|gate| {
+            let local = prefab::rotation(gate.rotation).inverse() * (point - gate.position);
+            gate.blocks().iter().any(|block| block_in_way(block, local))
+        })
+}
+
+/// The ball is taken as a box, which keeps it a little further from walls than it has to.
+fn block_in_way(block: &BlockDef, point: Vec3) -> bool {
+    let local = prefab::rotation(block.rotation).inverse() * (point - block.position);
+    let half = block.size / 2.0;
+    local.x.abs() < half.x + BALL_RADIUS
+        && local.z.abs() < half.z + BALL_RADIUS
+        && local.y < half.y
+        && local.y + 2.0 * BALL_RADIUS > -half.y
+}
+
+/// Distance between two boxes on the ground plane, zero when they overlap.
+fn horizontal_gap(min_a: Vec3, max_a: Vec3, min_b: Vec3, max_b: Vec3) -> f32 {
+    let x = (min_b.x - max_a.x).max(min_a.x - max_b.x).max(0.0);
+    let z = (min_b.z - max_a.z).max(min_a.z - max_b.z).max(0.0);
+    Vec2::new(x, z).length()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THICKNESS:f32 = 0.1;
+
+    /// A platform 2 wide with its top at `top`, from `near` along -Z for `length`.
+    fn platform(near: f32, length: f32, top: f32) -> PlatformDef {
+        PlatformDef::new(Vec3::new(2.0, THICKNESS, length), Vec3::new(0.0, top - THICKNESS / 2.0, near - length / 2.0))
+    }
+
+    fn wall(size: Vec3, position: Vec3) -> BlockDef {
+        BlockDef {
+            size,
+            position,
+            rotation: Vec3::ZERO,
+            color: None
+        }
+    }
+
+    /// Whether the ball dropped near z 0 gets to `point`.
+    fn reaches(platforms: Vec<PlatformDef>, point: Vec3) -> bool {
+        let level = Level {
+            platforms,
+            ..default()
+        };
+        NavGraph::new(&level).reachable(Vec3::new(0.0, 1.0, -1.0)).reaches(point)
+    }
+
+    #[test]
+    fn gap_within_reach_is_crossed() {
+        let drop = 1.0;
+        let gap = gap_reach(drop) - 0.05;
+        let far = platform(-4.0 - gap, 4.0, -drop);
+        assert!(reaches(vec![platform(0.0, 4.0, 0.0), far], Vec3::new(0.0, -drop + 0.5, -7.5 - gap)));
+    }
+
+    #[test]
+    fn gap_beyond_reach_is_not_crossed() {
+        let drop = 1.0;
+        let gap = gap_reach(drop) + 0.05;
+        let far = platform(-4.0 - gap, 4.0, -drop);
+        assert!(!reaches(vec![platform(0.0, 4.0, 0.0), far], Vec3::new(0.0, -drop + 0.5, -7.5 - gap)));
+    }
+
+    #[test]
+    fn wall_across_seals_the_corridor() {
+        let end = Vec3::new(0.0, 0.5, -7.5);
+        assert!(reaches(vec![platform(0.0, 8.0, 0.0)], end));
+
+        let mut sealed = platform(0.0, 8.0, 0.0);
+        sealed.walls.push(wall(Vec3::new(2.0, 0.4, 0.4), Vec3::new(0.0, 0.25, 0.0)));
+        assert!(!reaches(vec![sealed], end));
+    }
+
+    #[test]
+    fn step_up_only_within_step_height() {
+        let end = Vec3::new(0.0, 0.5, -7.5);
+        let low_step = platform(-4.0, 4.0, STEP_HEIGHT / 2.0);
+        assert!(reaches(vec![platform(0.0, 4.0, 0.0), low_step], end + Vec3::Y * STEP_HEIGHT / 2.0));
+
+        let high_step = platform(-4.0, 4.0, STEP_HEIGHT + 0.05);
+        assert!(!reaches(vec![platform(0.0, 4.0, 0.0), high_step], end + Vec3::Y * (STEP_HEIGHT + 0.05)));
+    }
+
+    #[test]
+    fn opening_narrower_than_the_ball_is_closed() {
+        //a railed platform 4 wide, a wall across leaves an opening at the right rail
+        let railed = |opening: f32| {
+            let mut platform = PlatformDef::new(Vec3::new(4.0, THICKNESS, 8.0), Vec3::new(0.0, -THICKNESS / 2.0, -4.0));
+            for side in [-1.0, 1.0] {
+                platform.walls.push(wall(Vec3::new(0.4, 0.4, 8.0), Vec3::new(side * 2.2, 0.25, 0.0)));
+            }
+            platform.walls.push(wall(Vec3::new(4.0 - opening, 0.4, 0.4), Vec3::new(-opening / 2.0, 0.25, 0.0)));
+            platform
+        };
+        let end = Vec3::new(0.0, 0.5, -7.5);
+        assert!(reaches(vec![railed(2.0 * BALL_RADIUS + 0.6)], end));
+        assert!(!reaches(vec![railed(2.0 * BALL_RADIUS - 0.2)], end));
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::lifetime::{self, Lifetime, LifetimeExpiredEvent, ShrinkOut};
+
+const POOL_SIZE:usize = 128;
+/// materials from opaque to invisible, particles step through them as they fade
+const FADE_STEPS:usize = 8;
+const GRAVITY:f32 = 9.81;
+/// share of the speed kept when bouncing off the ground
+const BOUNCE:f32 = 0.4;
+const PARTICLE_SIZE:f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParticleShape {
+    #[default]
+    Cube,
+    Sphere
+}
+
+/// How particles look: their shape, and their color going from start to end while fading out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParticleLook {
+    pub shape:ParticleShape,
+    pub color_start:(f32, f32, f32),
+    pub color_end:(f32, f32, f32),
+    /// how strongly the particles glow in their own color
+    pub emissive:f32
+}
+impl Default for ParticleLook {
+    fn default() -> Self {
+        Self {
+            shape: ParticleShape::Cube,
+            color_start: (1.0, 0.5, 0.0),
+            color_end: (1.0, 0.5, 0.0),
+            emissive: 1.0
+        }
+    }
+}
+
+/// A particle moving without physics, fading and shrinking while it has a `Lifetime`.
+#[derive(Component, Default)]
+pub struct Particle {
+    pub velocity:Vec3,
+    /// fraction of the velocity lost per second
+    pub drag:f32,
+    pub gravity_scale:f32,
+    /// height of the plane the particle bounces off
+    pub ground:f32,
+    pub size:f32,
+    /// index of the look, set by `Particles::emit`
+    pub look:usize
+}
+
+/// The meshes and fade materials shared by all particles with the same look.
+#[derive(Resource)]
+pub struct ParticleAssets {
+    cube:Handle<Mesh>,
+    sphere:Handle<Mesh>,
+    looks:Vec<LookHandles>,
+    names:HashMap<String, usize>
+}
+impl ParticleAssets {
+    fn mesh(&self, shape: ParticleShape) -> Handle<Mesh> {
+        match shape {
+            ParticleShape::Cube => self.cube.clone(),
+            ParticleShape::Sphere => self.sphere.clone()
+        }
+    }
+
+    fn add_look(&mut self, materials: &mut Assets<StandardMaterial>, look: &ParticleLook) -> usize {
+        let start = Vec3::from(look.color_start);
+        let end = Vec3::from(look.color_end);
+        let fade = (0..FADE_STEPS).map(|step| {
+            let t = step as f32 / FADE_STEPS as f32;
+            let color = start.lerp(end, t);
+            let alpha = 1.0 - t;
+            materials.add(StandardMaterial {
+                base_color: Color::srgba(color.x, color.y, color.z, alpha),
+                metallic: 0.5,
+                emissive: LinearRgba::rgb(color.x, color.y, color.z) * look.emissive * alpha,
+                alpha_mode: AlphaMode::Blend,
+                ..Default::default()
+            })
+        }).collect();
+        self.looks.push(LookHandles {
+            mesh: self.mesh(look.shape),
+            fade
+        });
+        self.looks.len() - 1
+    }
+}
+
+struct LookHandles {
+    mesh:Handle<Mesh>,
+    fade:Vec<Handle<StandardMaterial>>
+}
+
+/// Particles not in use, hidden until they are emitted again.
+#[derive(Resource, Default)]
+pub struct ParticlePool {
+    free:Vec<Entity>
+}
+
+/// Moves pooled particles, the effects only emit them.
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ParticlePool>()
+            .add_systems(Startup,setup)
+            .add_systems(Update,(update_particles,
+                                 return_to_pool).after(lifetime::tick_lifetimes));
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut pool: ResMut<ParticlePool>,
+) {
+    let mut assets = ParticleAssets {
+        cube: meshes.add(Mesh::from(Cuboid::new(PARTICLE_SIZE, PARTICLE_SIZE, PARTICLE_SIZE))),
+        sphere: meshes.add(Mesh::from(Sphere::new(PARTICLE_SIZE / 2.0))),
+        looks: Vec::new(),
+        names: HashMap::new()
+    };
+    //look 0 is used by the hidden particles in the pool
+    assets.add_look(&mut materials, &ParticleLook::default());
+    for _ in 0..POOL_SIZE {
+        let entity = spawn_hidden(&mut commands, &assets);
+        pool.free.push(entity);
+    }
+    commands.insert_resource(assets);
+}
+
+fn spawn_hidden(commands: &mut Commands, assets: &ParticleAssets) -> Entity {
+    commands
+        .spawn((
+            Mesh3d(assets.looks[0].mesh.clone()),
+            MeshMaterial3d(assets.looks[0].fade[0].clone()),
+            Transform::default(),
+            Visibility::Hidden
+        ))
+        .insert(Particle::default())
+        .id()
+}
+
+/// Takes particles from the pool, growing it when all are in use.
+#[derive(SystemParam)]
+pub struct Particles<'w, 's> {
+    commands:Commands<'w, 's>,
+    pool:ResMut<'w, ParticlePool>,
+    assets:ResMut<'w, ParticleAssets>,
+    materials:ResMut<'w, Assets<StandardMaterial>>
+}
+impl Particles<'_, '_> {
+    /// The index of the look called `name`, creating its materials the first time.
+    pub fn look(&mut self, name: &str, look: &ParticleLook) -> usize {
+        if let Some(&index) = self.assets.names.get(name) {
+            return index;
+        }
+        let index = self.assets.add_look(&mut self.materials, look);
+        self.assets.names.insert(name.to_string(), index);
+        index
+    }
+
+    /// Forgets the looks by name, so changed looks get new materials.
+    pub fn clear_looks(&mut self) {
+        self.assets.names.clear();
+    }
+
+    pub fn emit(&mut self, position: Vec3, look: usize, lifetime: f32, particle: Particle) {
+        let entity = match self.pool.free.pop() {
+            Some(entity) => entity,
+            None => spawn_hidden(&mut self.commands, &self.assets)
+        };
+        let handles = &self.assets.looks[look];
+        let scale = Vec3::splat(particle.size / PARTICLE_SIZE);
+        self.commands.entity(entity).insert((
+            Transform::from_translation(position).with_scale(scale),
+            Mesh3d(handles.mesh.clone()),
+            MeshMaterial3d(handles.fade[0].clone()),
+            Visibility::Visible,
+            Lifetime::expire_after(lifetime).with_event(),
+            ShrinkOut {
+                from: 0.0,
+                scale
+            },
+            Particle {
+                look,
+                ..particle
+            }
+        ));
+    }
+}
+
+fn update_particles(
+    time: Res<Time>,
+    assets: Res<ParticleAssets>,
+    mut query: Query<(&mut Particle, &Lifetime, &mut Transform, &mut MeshMaterial3d<StandardMaterial>)>,
+){
+    let dt = time.delta_secs();
+    for (mut particle, lifetime, mut transform, mut material) in query.iter_mut() {
+        let drag = (1.0 - particle.drag * dt).max(0.0);
+        particle.velocity.y -= GRAVITY * particle.gravity_scale * dt;
+        particle.velocity *= drag;
+        transform.translation += particle.velocity * dt;
+        if transform.translation.y < particle.ground && particle.velocity.y < 0.0 {
+            transform.translation.y = particle.ground;
+            particle.velocity.y = -particle.velocity.y * BOUNCE;
+            particle.velocity.x *= BOUNCE;
+            particle.velocity.z *= BOUNCE;
+        }
+
+        //the materials are shared, so fading steps through them instead of using `FadeOut`
+        let step = ((lifetime.fraction() * FADE_STEPS as f32) as usize).min(FADE_STEPS - 1);
+        let fade = &assets.looks[particle.look].fade;
+        if material.0 != fade[step] {
+            material.0 = fade[step].clone();
+        }
+    }
+}
+
+fn return_to_pool(
+    mut event_expired: EventReader<LifetimeExpiredEvent>,
+    mut pool: ResMut<ParticlePool>,
+    mut query: Query<&mut Visibility, With<Particle>>,
+){
+    for event in event_expired.read() {
+        if let Ok(mut visibility) = query.get_mut(event.0) {
+            *visibility = Visibility::Hidden;
+            pool.free.push(event.0);
+        }
+    }
+}
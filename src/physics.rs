@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::camera;
+
+/// How often the physics steps, independent of the frame rate.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct PhysicsSettings {
+    /// fixed ticks per second
+    pub rate:f64,
+    /// rapier steps per fixed tick
+    pub substeps:usize
+}
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            rate: 60.0,
+            substeps: 2
+        }
+    }
+}
+
+/// Transform of a body after the last two fixed ticks.
+#[derive(Component)]
+pub struct TickTransforms {
+    previous:Transform,
+    current:Transform
+}
+impl TickTransforms {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            previous: transform,
+            current: transform
+        }
+    }
+
+    /// Moves the body without interpolating from where it was.
+    pub fn teleport(&mut self, transform: Transform) {
+        self.previous = transform;
+        self.current = transform;
+    }
+}
+
+/// A mesh that follows `body` smoothly between the fixed ticks.
+#[derive(Component)]
+pub struct InterpolatedVisual {
+    pub body:Entity
+}
+
+/// Runs rapier in `FixedPostUpdate` at the rate of `PhysicsSettings`
+/// and interpolates the visuals of bodies in between.
+pub struct PhysicsPlugin {
+    pub settings:PhysicsSettings
+}
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<RapierPhysicsPlugin<NoUserData>>() {
+            app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default().in_fixed_schedule());
+        }
+        app
+            .insert_resource(self.settings)
+            .insert_resource(Time::<Fixed>::from_hz(self.settings.rate))
+            .insert_resource(TimestepMode::Fixed {
+                dt: (1.0 / self.settings.rate) as f32,
+                substeps: self.settings.substeps
+            })
+            .add_systems(FixedPostUpdate,record_tick_transforms.after(PhysicsSet::Writeback))
+            .add_systems(Update,interpolate_visuals.before(camera::move_camera));
+    }
+}
+
+fn record_tick_transforms(
+    mut query: Query<(&Transform, &mut TickTransforms)>,
+){
+    for (transform, mut tick_transforms) in query.iter_mut() {
+        tick_transforms.previous = tick_transforms.current;
+        tick_transforms.current = *transform;
+    }
+}
+
+fn interpolate_visuals(
+    time: Res<Time<Fixed>>,
+    query_bodies: Query<&TickTransforms>,
+    mut query_visuals: Query<(&InterpolatedVisual, &mut Transform)>,
+){
+    let t = time.overstep_fraction();
+    for (visual, mut transform) in query_visuals.iter_mut() {
+        let Ok(tick_transforms) = query_bodies.get(visual.body) else {
+            continue;
+        };
+        let previous = tick_transforms.previous;
+        let current = tick_transforms.current;
+        transform.translation = previous.translation.lerp(current.translation, t);
+        transform.rotation = previous.rotation.slerp(current.rotation, t);
+    }
+}
@@ -0,0 +1,196 @@
+//! Builders for the pieces courses are made of, each spawning mesh, material,
+//! collider and children the same way wherever it is used.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::asset_cache::CachedAssets;
+use crate::camera::ThirdPersonTarget;
+use crate::level::{BlockDef, GateDef, Goal, GoalDef, PlatformDef, Wall};
+use crate::physics::{InterpolatedVisual, TickTransforms};
+use crate::{Ball, Cheese};
+
+pub const WALL_COLOR:(f32, f32, f32) = (0.5, 0.5, 0.5);
+pub const DOOR_COLOR:(f32, f32, f32) = (0.0, 0.5, 0.0);
+pub const BALL_COLOR:(f32, f32, f32) = (0.0, 0.0, 1.0);
+const CHEESE_MODEL:&str = "models/cheese.glb#Scene0";
+
+/// A box the ball bounces off, `default_color` is used when the block has none.
+pub fn spawn_wall(
+    commands: &mut Commands,
+    cached_assets: &mut CachedAssets,
+    block: &BlockDef,
+    default_color: (f32, f32, f32)
+) -> Entity {
+    let size = block.size;
+    commands
+        .spawn((
+            Mesh3d(cached_assets.cuboid(size)),
+            MeshMaterial3d(cached_assets.material(block.color.unwrap_or(default_color), true)),
+            Transform {
+                translation: block.position,
+                rotation: rotation(block.rotation),
+                ..Default::default()
+            }
+        ))
+        .insert(Collider::cuboid(size.x/2.0, size.y/2.0, size.z/2.0))
+        .insert(Wall)
+        .id()
+}
+
+/// An invisible sensor that finishes the level.
+pub fn spawn_goal(
+    commands: &mut Commands,
+    goal: &GoalDef
+) -> Entity {
+    let size = goal.size;
+    commands
+        .spawn((
+            Transform::from_translation(goal.position),
+            Visibility::default()
+        ))
+        .insert(Collider::cuboid(size.x/2.0, size.y/2.0, size.z/2.0))
+        .insert(Sensor)
+        .insert(ActiveEvents::COLLISION_EVENTS)
+        .insert(Goal)
+        .id()
+}
+
+/// Two posts and a lintel standing on `gate.position`, with a goal in the opening if asked for.
+pub fn spawn_gate(
+    commands: &mut Commands,
+    cached_assets: &mut CachedAssets,
+    gate: &GateDef
+) -> Entity {
+    let mut children_list:Vec<Entity> = gate.blocks().iter()
+        .map(|block| spawn_wall(commands, cached_assets, block, DOOR_COLOR))
+        .collect();
+    if let Some(goal) = gate.opening_goal() {
+        children_list.push(spawn_goal(commands, &goal));
+    }
+    commands
+        .spawn((
+            Transform {
+                translation: gate.position,
+                rotation: rotation(gate.rotation),
+                ..Default::default()
+            },
+            Visibility::default()
+        ))
+        .add_children(&children_list)
+        .id()
+}
+
+/// The entities of a platform, in the order of the `PlatformDef` lists they were spawned from.
+pub struct PlatformEntities {
+    pub platform:Entity,
+    pub walls:Vec<Entity>,
+    pub doors:Vec<Entity>,
+    pub gates:Vec<Entity>,
+    pub goal:Option<Entity>
+}
+
+/// A fixed platform carrying its walls, doors, gates and goal as children.
+pub fn spawn_platform(
+    commands: &mut Commands,
+    cached_assets: &mut CachedAssets,
+    platform: &PlatformDef
+) -> PlatformEntities {
+    let walls:Vec<Entity> = platform.walls.iter()
+        .map(|wall| spawn_wall(commands, cached_assets, wall, WALL_COLOR))
+        .collect();
+    let doors:Vec<Entity> = platform.doors.iter()
+        .map(|door| spawn_wall(commands, cached_assets, door, DOOR_COLOR))
+        .collect();
+    let gates:Vec<Entity> = platform.gates.iter()
+        .map(|gate| spawn_gate(commands, cached_assets, gate))
+        .collect();
+    let goal = platform.goal.as_ref().map(|goal| spawn_goal(commands, goal));
+
+    let mut children_list:Vec<Entity> = Vec::new();
+    children_list.extend(walls.iter());
+    children_list.extend(doors.iter());
+    children_list.extend(gates.iter());
+    children_list.extend(goal.iter());
+    let entity = commands
+        .spawn((
+            Mesh3d(cached_assets.cuboid(platform.size)),
+            MeshMaterial3d(cached_assets.material(platform.color, true)),
+            Transform {
+                translation: platform.position,
+                rotation: rotation(platform.rotation),
+                ..Default::default()
+            }
+        ))
+        .add_children(&children_list)
+        .insert(RigidBody::Fixed)
+        .insert(Sleeping::disabled())
+        .insert(Collider::cuboid(platform.size.x/2.0, platform.size.y/2.0, platform.size.z/2.0))
+        .id();
+    PlatformEntities {
+        platform: entity,
+        walls,
+        doors,
+        gates,
+        goal
+    }
+}
+
+/// A cheese model, falling onto what is below it.
+/// Its collider is a cylinder around the model, so it is the same whether or not the model can be loaded.
+pub fn spawn_cheese(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec3
+) -> Entity {
+    commands.spawn((
+                SceneRoot( asset_server.load(CHEESE_MODEL)),
+                Transform::from_translation(position)
+    ))
+        .insert(RigidBody::Dynamic)
+        .insert(Sleeping::disabled())
+        .insert(Collider::cylinder(0.15, 0.3))
+        .insert(ActiveEvents::COLLISION_EVENTS)
+        .insert(Cheese{})
+        .id()
+}
+
+/// The ball's body and the mesh following it between fixed ticks, in that order.
+pub fn spawn_ball(
+    commands: &mut Commands,
+    cached_assets: &mut CachedAssets,
+    position: Vec3
+) -> (Entity, Entity) {
+    let ball_transform = Transform::from_translation(position);
+    let ball = commands
+    .spawn(ball_transform)
+        .insert(RigidBody::Dynamic)
+        .insert(Sleeping::disabled())
+        .insert(Collider::ball(0.5))
+        .insert(Velocity::zero())
+        .insert(ExternalForce {
+            ..Default::default()
+        })
+        .insert(ActiveEvents::COLLISION_EVENTS)
+        .insert(TickTransforms::new(ball_transform))
+        .insert(Ball{})
+        .id();
+    let visual = commands
+    .spawn((
+        Mesh3d(cached_assets.sphere(0.5)),
+        MeshMaterial3d(cached_assets.material(BALL_COLOR, false)),
+        ball_transform
+    ))
+        .insert(InterpolatedVisual { body: ball })
+        .insert(ThirdPersonTarget{})
+        .id();
+    (ball, visual)
+}
+
+/// euler angles in degrees to a rotation
+pub fn rotation(degrees: Vec3) -> Quat {
+    Quat::from_euler(EulerRot::XYZ,
+                     degrees.x.to_radians(),
+                     degrees.y.to_radians(),
+                     degrees.z.to_radians())
+}
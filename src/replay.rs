@@ -0,0 +1,190 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::endless::EndlessRun;
+use crate::input::{self, TickInput};
+use crate::level::{self, CurrentLevel, LevelSequence, LevelStartedEvent};
+use crate::rng::GameRng;
+use crate::state::GameState;
+use crate::tilt::ControlMode;
+
+/// where the last attempt is written when it ends
+pub const REPLAY_FILE:&str = "replays/last.replay.ron";
+
+/// The file attempts are saved to, `REPLAY_FILE` unless set otherwise.
+#[derive(Resource)]
+pub struct ReplayPath(pub String);
+impl Default for ReplayPath {
+    fn default() -> Self {
+        Self(REPLAY_FILE.to_string())
+    }
+}
+
+/// One attempt at a level: the level, the seed of `GameRng`, the control mode it started in
+/// and the input of every fixed tick.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+    pub level:String,
+    pub seed:u64,
+    #[serde(default)]
+    pub control_mode:ControlMode,
+    pub ticks:Vec<TickInput>
+}
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Could not access replay: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse replay: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Could not write replay: {0}")]
+    Write(#[from] ron::Error),
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text = ron::ser::to_string_pretty(self, PrettyConfig::default().compact_arrays(true))?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// Whether the ticks are recorded from the player or played back from a replay.
+#[derive(Resource)]
+pub enum ReplayMode {
+    Record(Replay),
+    Play {
+        replay:Replay,
+        tick:usize
+    }
+}
+impl Default for ReplayMode {
+    fn default() -> Self {
+        Self::Record(Replay::default())
+    }
+}
+impl ReplayMode {
+    /// true once every tick of a played back replay has been used
+    pub fn finished(&self) -> bool {
+        match self {
+            Self::Record(_) => false,
+            Self::Play { replay, tick } => *tick > replay.ticks.len()
+        }
+    }
+}
+
+/// Records every attempt at a level and saves it when the attempt ends,
+/// or replaces the player's input with a replay. Endless runs are not recorded,
+/// their chunks are streamed in depending on frame timing.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ReplayMode>()
+            .init_resource::<ReplayPath>()
+            .add_systems(Update,begin_replay
+                .after(level::start_level)
+                .run_if(in_state(GameState::Playing)))
+            .add_systems(FixedPreUpdate,replay_tick
+                .after(input::sample_input)
+                .run_if(in_state(GameState::Playing)))
+            .add_systems(OnEnter(GameState::LevelComplete),save_replay)
+            .add_systems(OnEnter(GameState::GameOver),save_replay)
+            .add_systems(OnEnter(GameState::Finished),save_replay)
+            .add_systems(OnEnter(GameState::MainMenu),save_replay);
+    }
+}
+
+/// Sets up `app` to play `replay` back instead of starting in the main menu.
+pub fn play(app: &mut App, replay: Replay) {
+    app
+        .insert_resource(LevelSequence {
+            levels: vec![replay.level.clone()],
+            index: 0
+        })
+        .insert_resource(replay.control_mode)
+        .insert_resource(ReplayMode::Play { replay, tick: 0 })
+        .insert_state(GameState::Playing);
+}
+
+pub fn begin_replay(
+    mut event_level_started: EventReader<LevelStartedEvent>,
+    mut mode: ResMut<ReplayMode>,
+    mut game_rng: ResMut<GameRng>,
+    mut control_mode: ResMut<ControlMode>,
+){
+    for event in event_level_started.read() {
+        match mode.as_mut() {
+            ReplayMode::Record(replay) => {
+                //every attempt starts the streams over, so it plays back the same
+                let seed = game_rng.seed();
+                game_rng.reseed(seed);
+                *replay = Replay {
+                    level: event.path.clone(),
+                    seed,
+                    control_mode: *control_mode,
+                    ticks: Vec::new()
+                };
+            }
+            ReplayMode::Play { replay, tick } => {
+                *tick = 0;
+                game_rng.reseed(replay.seed);
+                //toggles during an earlier playthrough don't carry over to a restart
+                *control_mode = replay.control_mode;
+            }
+        }
+    }
+}
+
+fn replay_tick(
+    current_level: Res<CurrentLevel>,
+    endless: Res<EndlessRun>,
+    mut mode: ResMut<ReplayMode>,
+    mut tick_input: ResMut<TickInput>,
+){
+    if !current_level.spawned || endless.active {
+        return;
+    }
+    match mode.as_mut() {
+        ReplayMode::Record(replay) => replay.ticks.push(*tick_input),
+        ReplayMode::Play { replay, tick } => {
+            *tick_input = replay.ticks.get(*tick).copied().unwrap_or_default();
+            if *tick == replay.ticks.len() {
+                info!("replay finished after {} ticks", tick);
+            }
+            *tick += 1;
+        }
+    }
+}
+
+fn save_replay(
+    path: Res<ReplayPath>,
+    mut mode: ResMut<ReplayMode>,
+){
+    let ReplayMode::Record(replay) = mode.as_mut() else {
+        return;
+    };
+    if replay.ticks.is_empty() {
+        return;
+    }
+    match replay.save(&path.0) {
+        Ok(()) => info!("replay saved to {}", path.0),
+        Err(error) => warn!("{}", error)
+    }
+    replay.ticks.clear();
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// The parts of the game drawing random numbers. Each gets its own stream,
+/// so one of them drawing more numbers does not change what the others get.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    Effects,
+    Placement
+}
+
+/// All gameplay randomness, derived from one seed.
+#[derive(Resource)]
+pub struct GameRng {
+    seed:u64,
+    streams:HashMap<RngStream, StdRng>
+}
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: HashMap::new()
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts every stream over from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.streams.clear();
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        let seed = self.seed;
+        self.streams.entry(stream).or_insert_with(|| {
+            //spread the streams apart, same constant as splitmix64
+            StdRng::seed_from_u64(seed ^ (stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+        })
+    }
+}
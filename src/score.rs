@@ -0,0 +1,251 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::effects::CreateEffectEvent;
+use crate::endless::EndlessRun;
+use crate::level::{CurrentLevel, Goal, LevelCompleteEvent};
+use crate::lifetime::{Lifetime, ShrinkOut};
+use crate::physics::TickTransforms;
+use crate::state::GameState;
+use crate::{Ball, Cheese};
+
+pub const LIVES:i32 = 3;
+/// points for a cheese, a unit of distance is worth one
+pub const CHEESE_POINTS:i32 = 10;
+const CHEESE_SHRINK_TIME:f32 = 0.3;
+
+#[derive(Resource)]
+pub struct Score {
+    pub cheese:i32,
+    pub level:i32,
+    pub lives:i32,
+    /// how far an endless run got
+    pub distance:i32
+}
+impl Default for Score{
+    fn default() -> Self {
+        Self {
+            cheese:0,
+            level:1,
+            lives:LIVES,
+            distance:0,
+        }
+    }
+}
+impl Score {
+    /// points of an endless run
+    pub fn total(&self) -> i32 {
+        self.distance + self.cheese * CHEESE_POINTS
+    }
+}
+
+#[derive(Component)]
+struct Cheesetext;
+
+#[derive(Component)]
+struct Leveltext;
+
+#[derive(Component)]
+struct Livestext;
+
+/// Cheese pickups, the goal and losing lives.
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Score::default())
+            .add_systems(FixedUpdate,(collision,
+                                 fall_off)
+                .run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Shows the score in the corners of the window.
+pub struct ScoreboardPlugin;
+
+impl Plugin for ScoreboardPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup,setup_scoreboard)
+            .add_systems(Update,scoreboard);
+    }
+}
+
+fn setup_scoreboard(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    // scoreboard
+    commands.spawn((
+        Text::new("Cheese:"),
+        TextFont{
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 40.0,
+            ..default()
+        },
+        TextColor(Color::srgb(0.5, 0.5, 1.0)),
+        TextLayout::new_with_justify(JustifyText::Center),
+                   // Set the style of the Node itself.
+       Node {
+           position_type: PositionType::Absolute,
+           top: Val::Px(5.0),
+           left: Val::Px(5.0),
+           ..default()
+       }
+    ))
+        .insert(Cheesetext);
+
+    commands.spawn((
+        Text::new("Level:"),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 40.0,
+            ..default()
+        },
+        TextColor(Color::srgb(0.5, 0.5, 1.0)),
+        TextLayout::new_with_justify(JustifyText::Center),
+                   // Set the style of the Node itself.
+       Node {
+           position_type: PositionType::Absolute,
+           top: Val::Px(5.0),
+           right: Val::Px(25.0),
+           ..default()
+       }
+    )).insert(Leveltext);
+
+    commands.spawn((
+        Text::new("Lives:"),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 40.0,
+            ..default()
+        },
+        TextColor(Color::srgb(0.5, 0.5, 1.0)),
+        TextLayout::new_with_justify(JustifyText::Center),
+       Node {
+           position_type: PositionType::Absolute,
+           top: Val::Px(50.0),
+           left: Val::Px(5.0),
+           ..default()
+       }
+    )).insert(Livestext);
+}
+
+fn scoreboard(
+    score: Res<Score>,
+    endless: Res<EndlessRun>,
+    mut cheese_query: Query<&mut Text, (With<Cheesetext>, Without<Leveltext>, Without<Livestext>)>,
+    mut level_query: Query<&mut Text, (With<Leveltext>, Without<Livestext>)>,
+    mut lives_query: Query<&mut Text, With<Livestext>>,
+) {
+    let mut text = cheese_query.single_mut();
+    text.0 = format!("Cheese: {}", score.cheese);
+
+    let mut level_text = level_query.single_mut();
+    level_text.0 = if endless.active {
+        format!("Score: {} ({}m)", score.total(), score.distance)
+    } else {
+        format!("Level: {}", score.level)
+    };
+
+    let mut lives_text = lives_query.single_mut();
+    lives_text.0 = format!("Lives: {}", score.lives);
+}
+
+pub fn fall_off(
+    mut score: ResMut<Score>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut query_ball: Query<(&mut Transform, &mut TickTransforms, &mut Velocity, &mut ExternalForce), With<Ball>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut event_create_effect: EventWriter<CreateEffectEvent>,
+){
+    for (mut transform, mut tick_transforms, mut velocity, mut ext_force) in query_ball.iter_mut() {
+        //the state only changes next frame, more ticks may run until then
+        if current_level.ended || transform.translation.y > current_level.kill_height {
+            continue;
+        }
+        score.lives -= 1;
+        if score.lives <= 0 {
+            current_level.ended = true;
+            next_state.set(GameState::GameOver);
+            continue;
+        }
+        //respawn
+        *transform = Transform::from_translation(current_level.ball_start);
+        tick_transforms.teleport(*transform);
+        *velocity = Velocity::zero();
+        ext_force.force = Vec3::ZERO;
+        ext_force.torque = Vec3::ZERO;
+        event_create_effect.send(CreateEffectEvent::new("respawn", current_level.ball_start));
+    }
+}
+
+/// What the ball can run into.
+#[derive(SystemParam)]
+pub struct CollisionTargets<'w, 's> {
+    ball:Query<'w, 's, Entity, With<Ball>>,
+    cheese:Query<'w, 's, (Entity, &'static Transform), With<Cheese>>,
+    goals:Query<'w, 's, Entity, With<Goal>>
+}
+
+pub fn collision(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut score: ResMut<Score>,
+    mut current_level: ResMut<CurrentLevel>,
+    targets: CollisionTargets,
+    mut event_create_effect: EventWriter<CreateEffectEvent>,
+    mut event_level_complete: EventWriter<LevelCompleteEvent>,
+    mut commands: Commands
+){
+    let Ok(entity_ball) = targets.ball.get_single() else {
+        return;
+    };
+    if current_level.ended {
+        collision_events.clear();
+        return;
+    }
+    let mut eaten:Vec<Entity> = Vec::new();
+    for e in collision_events.read(){
+        //println!("{:?}",e);
+        match e {
+            CollisionEvent::Started(e1,e2,_) => {
+                if e1 == &entity_ball || e2 == &entity_ball {
+                    for entity in [e1, e2] {
+                        let Ok((entity_cheese, cheese_transform)) = targets.cheese.get(*entity) else {
+                            continue;
+                        };
+                        if eaten.contains(&entity_cheese) {
+                            continue;
+                        }
+                        eaten.push(entity_cheese);
+                        //no longer collectable, shrinks away where it was
+                        commands.entity(entity_cheese)
+                            .remove::<(Cheese, RigidBody, Collider, ActiveEvents)>()
+                            .insert(Lifetime::despawn_after(CHEESE_SHRINK_TIME))
+                            .insert(ShrinkOut {
+                                from: 0.0,
+                                scale: cheese_transform.scale
+                            });
+                        score.cheese += 1;
+                        event_create_effect.send(CreateEffectEvent::new("cheese", cheese_transform.translation));
+                        if current_level.cheese_left > 0 {
+                            current_level.cheese_left -= 1;
+                            if current_level.cheese_left == 0 && !current_level.has_goal {
+                                event_level_complete.send(LevelCompleteEvent);
+                            }
+                        }
+                    }
+                    for entity_goal in targets.goals.iter(){
+                        if (e1 == &entity_goal || e2 == &entity_goal)
+                            && (current_level.cheese_left == 0 || !current_level.goal_requires_cheese) {
+                            event_level_complete.send(LevelCompleteEvent);
+                        }
+                    }
+                }
+            }
+            CollisionEvent::Stopped(_,_,_)=> {}
+        }
+    }
+}
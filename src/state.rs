@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::endless::EndlessRun;
+use crate::input::PlayerInput;
+use crate::level::{CurrentLevel, LevelSequence};
+use crate::score::Score;
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    Playing,
+    Paused,
+    LevelComplete,
+    GameOver,
+    /// all levels of the sequence are done
+    Finished,
+    /// the key rebinding screen
+    Bindings,
+    /// the level editor, entered from play
+    Editor,
+}
+
+/// The game flow between menu, levels and end screens, with physics paused outside of play
+/// and the game's clock stopped on the pause screen.
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_state::<GameState>()
+            .enable_state_scoped_entities::<GameState>()
+            .add_systems(OnEnter(GameState::Playing),resume_physics)
+            .add_systems(OnExit(GameState::Playing),pause_physics)
+            //particles and lifetimes stop with the clock
+            .add_systems(OnEnter(GameState::Paused),pause_time)
+            .add_systems(OnExit(GameState::Paused),resume_time)
+            .add_systems(Update,(start_game.run_if(in_state(GameState::MainMenu)),
+                                 toggle_pause,
+                                 quit_to_menu.run_if(in_state(GameState::Paused)),
+                                 continue_game));
+    }
+}
+
+/// The menu, pause and end screens.
+pub struct ScreensPlugin;
+
+impl Plugin for ScreensPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(GameState::MainMenu),spawn_main_menu)
+            .add_systems(OnEnter(GameState::Paused),spawn_pause_screen)
+            .add_systems(OnEnter(GameState::LevelComplete),spawn_level_complete_screen)
+            .add_systems(OnEnter(GameState::GameOver),spawn_game_over_screen)
+            .add_systems(OnEnter(GameState::Finished),spawn_finished_screen)
+            .add_systems(Update,open_bindings.run_if(in_state(GameState::MainMenu)));
+    }
+}
+
+pub fn start_game(
+    player_input: Res<PlayerInput>,
+    asset_server: Res<AssetServer>,
+    mut score: ResMut<Score>,
+    mut sequence: ResMut<LevelSequence>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if !player_input.confirm {
+        return;
+    }
+    *score = Score::default();
+    *sequence = LevelSequence::default();
+    if let Some(path) = sequence.current() {
+        *current_level = CurrentLevel::load(&asset_server, path);
+    }
+    next_state.set(GameState::Playing);
+}
+
+pub fn open_bindings(
+    player_input: Res<PlayerInput>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if player_input.bindings {
+        next_state.set(GameState::Bindings);
+    }
+}
+
+pub fn toggle_pause(
+    player_input: Res<PlayerInput>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if !player_input.pause {
+        return;
+    }
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+pub fn quit_to_menu(
+    player_input: Res<PlayerInput>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if player_input.quit {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+pub fn continue_game(
+    player_input: Res<PlayerInput>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+){
+    if !player_input.confirm {
+        return;
+    }
+    match state.get() {
+        GameState::LevelComplete => next_state.set(GameState::Playing),
+        GameState::GameOver | GameState::Finished => next_state.set(GameState::MainMenu),
+        _ => {}
+    }
+}
+
+pub fn resume_physics(
+    mut query_config: Query<&mut RapierConfiguration>,
+){
+    for mut config in query_config.iter_mut() {
+        config.physics_pipeline_active = true;
+    }
+}
+
+pub fn pause_physics(
+    mut query_config: Query<&mut RapierConfiguration>,
+){
+    for mut config in query_config.iter_mut() {
+        config.physics_pipeline_active = false;
+    }
+}
+
+pub fn pause_time(
+    mut time: ResMut<Time<Virtual>>,
+){
+    time.pause();
+}
+
+pub fn resume_time(
+    mut time: ResMut<Time<Virtual>>,
+){
+    time.unpause();
+}
+
+pub fn spawn_main_menu(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+){
+    spawn_screen(commands, &asset_server, GameState::MainMenu,
+                 "Cheeseball", "Press Enter to start, E for an endless run, K for key bindings");
+}
+
+pub fn spawn_pause_screen(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+){
+    spawn_screen(commands, &asset_server, GameState::Paused,
+                 "Paused", "Esc to resume, Q to quit");
+}
+
+pub fn spawn_level_complete_screen(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    score: Res<Score>,
+){
+    spawn_screen(commands, &asset_server, GameState::LevelComplete,
+                 &format!("Level {} complete", score.level), "Press Enter to continue");
+}
+
+pub fn spawn_game_over_screen(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    score: Res<Score>,
+    endless: Res<EndlessRun>,
+){
+    let subtitle = if endless.active {
+        format!("Score: {} ({}m, {} cheese) - press Enter", score.total(), score.distance, score.cheese)
+    } else {
+        "Press Enter".to_string()
+    };
+    spawn_screen(commands, &asset_server, GameState::GameOver,
+                 "Game over", &subtitle);
+}
+
+pub fn spawn_finished_screen(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    score: Res<Score>,
+){
+    spawn_screen(commands, &asset_server, GameState::Finished,
+                 "All levels complete!", &format!("Cheese: {} - press Enter", score.cheese));
+}
+
+fn spawn_screen(
+    mut commands: Commands,
+    asset_server: &AssetServer,
+    state: GameState,
+    title: &str,
+    subtitle: &str
+){
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(20.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+            StateScoped(state)
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(title),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 60.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 0.8, 0.0))
+            ));
+            parent.spawn((
+                Text::new(subtitle),
+                TextFont {
+                    font,
+                    font_size: 30.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.5, 0.5, 1.0))
+            ));
+        });
+}
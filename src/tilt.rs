@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::{relative_to, TickInput};
+use crate::level::{self, LevelStartedEvent};
+use crate::state::GameState;
+use crate::Ball;
+
+const GRAVITY:f32 = 9.81;
+/// the steepest the board can be tilted, in radians
+pub const MAX_TILT:f32 = 0.25;
+const TILT_SPEED:f32 = 4.0;
+
+/// How the player steers the ball: pushing it, or tilting the board like in Monkey Ball.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ControlMode {
+    #[default]
+    Force,
+    Tilt
+}
+
+/// Tilt of the board relative to the camera, x to the right and y forward, each in -1..1.
+#[derive(Resource, Default)]
+pub struct BoardTilt {
+    pub current:Vec2
+}
+
+/// Switching between pushing the ball and tilting the board.
+pub struct TiltPlugin;
+
+impl Plugin for TiltPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ControlMode>()
+            .init_resource::<BoardTilt>()
+            .add_systems(Update,reset_tilt.after(level::start_level))
+            .add_systems(FixedUpdate,(toggle_control_mode,
+                                 input_tilt.run_if(tilt_mode))
+                .run_if(in_state(GameState::Playing)));
+    }
+}
+
+pub fn force_mode(mode: Res<ControlMode>) -> bool {
+    *mode == ControlMode::Force
+}
+
+pub fn tilt_mode(mode: Res<ControlMode>) -> bool {
+    *mode == ControlMode::Tilt
+}
+
+pub fn toggle_control_mode(
+    tick_input: Res<TickInput>,
+    mut mode: ResMut<ControlMode>,
+    mut tilt: ResMut<BoardTilt>,
+    mut query_config: Query<&mut RapierConfiguration>,
+    mut query_forces: Query<&mut ExternalForce, With<Ball>>,
+){
+    if !tick_input.toggle_mode {
+        return;
+    }
+    *mode = match *mode {
+        ControlMode::Force => ControlMode::Tilt,
+        ControlMode::Tilt => ControlMode::Force
+    };
+    info!("control mode: {:?}", *mode);
+
+    tilt.current = Vec2::ZERO;
+    for mut config in query_config.iter_mut() {
+        config.gravity = Vec3::NEG_Y * GRAVITY;
+    }
+    for mut ext_force in query_forces.iter_mut() {
+        ext_force.force = Vec3::ZERO;
+    }
+}
+
+/// Levels start with a level board, whatever the last attempt left behind.
+pub fn reset_tilt(
+    mut event_level_started: EventReader<LevelStartedEvent>,
+    mut tilt: ResMut<BoardTilt>,
+    mut query_config: Query<&mut RapierConfiguration>,
+){
+    if event_level_started.read().count() == 0 {
+        return;
+    }
+    tilt.current = Vec2::ZERO;
+    for mut config in query_config.iter_mut() {
+        config.gravity = Vec3::NEG_Y * GRAVITY;
+    }
+}
+
+pub fn input_tilt(
+    tick_input: Res<TickInput>,
+    time: Res<Time>,
+    mut tilt: ResMut<BoardTilt>,
+    mut query_config: Query<&mut RapierConfiguration>,
+){
+    let t = (TILT_SPEED * time.delta_secs()).min(1.0);
+    tilt.current = tilt.current.lerp(tick_input.movement, t);
+
+    //tilt is relative to where the camera looked when the tick was sampled
+    let direction = relative_to(tilt.current, tick_input.forward);
+    let angle = direction.length() * MAX_TILT;
+    let gravity = (Vec3::NEG_Y * angle.cos() + direction.normalize_or_zero() * angle.sin()) * GRAVITY;
+
+    for mut config in query_config.iter_mut() {
+        config.gravity = gravity;
+    }
+}
@@ -0,0 +1,59 @@
+//! Plays the first level without a window, like `--headless scripts/collect_cheese.ron`,
+//! and plays a saved run back like `--replay <file> --headless`.
+
+use bevy::prelude::*;
+use cheeseball::headless::{self, ScriptStep, ScriptedInput};
+use cheeseball::replay::{Replay, ReplayPath};
+use cheeseball::score::Score;
+use cheeseball::state::GameState;
+use cheeseball::Ball;
+
+#[test]
+fn holding_forward_collects_the_cheese() {
+    //the walls keep the ball on the cheese's line and stop it in the goal
+    let script = ScriptedInput::new(vec![
+        ScriptStep {
+            frames: 600,
+            movement: Vec2::Y,
+            ..default()
+        },
+    ]);
+    let mut app = headless::headless_app(script, Some(1));
+
+    assert!(headless::run_script(&mut app), "the level did not load");
+    assert_eq!(app.world().resource::<Score>().cheese, 1);
+    assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::LevelComplete);
+}
+
+/// Where the ball ended up, the score and the state the run ended in.
+fn outcome(app: &mut App) -> (Transform, [i32; 4], GameState) {
+    let ball = *app.world_mut()
+        .query_filtered::<&Transform, With<Ball>>()
+        .single(app.world());
+    let score = app.world().resource::<Score>();
+    let state = *app.world().resource::<State<GameState>>().get();
+    (ball, [score.cheese, score.level, score.lives, score.distance], state)
+}
+
+#[test]
+fn saved_replay_plays_back_the_same_run() {
+    let path = std::env::temp_dir().join(format!("cheeseball-{}.replay.ron", std::process::id()));
+    let script = ScriptedInput::new(vec![
+        ScriptStep {
+            frames: 600,
+            movement: Vec2::Y,
+            ..default()
+        },
+    ]);
+    let mut recorded = headless::headless_app(script, Some(7));
+    recorded.insert_resource(ReplayPath(path.to_string_lossy().into_owned()));
+    assert!(headless::run_script(&mut recorded), "the level did not load");
+
+    //saved when the level was completed
+    let replay = Replay::load(&path).expect("the run was not saved");
+    let _ = std::fs::remove_file(&path);
+    let mut played = headless::replay_app(replay);
+    assert!(headless::run_replay(&mut played), "the level did not load");
+
+    assert_eq!(outcome(&mut recorded), outcome(&mut played));
+}